@@ -0,0 +1,66 @@
+// Runs loops of doubling length and prints the time spent per iteration.
+// A loop is linear when the time per iteration stays roughly flat as it grows, whatever is in scope.
+//
+// Run with `cargo bench`.
+
+#[allow(dead_code)]
+#[path = "../src/built_in_functions.rs"]
+mod built_in_functions;
+#[allow(dead_code)]
+#[path = "../src/environment.rs"]
+mod environment;
+#[allow(dead_code)]
+#[path = "../src/interpreter.rs"]
+mod interpreter;
+#[allow(dead_code)]
+#[path = "../src/lexer.rs"]
+mod lexer;
+#[allow(dead_code)]
+#[path = "../src/methods.rs"]
+mod methods;
+#[allow(dead_code)]
+#[path = "../src/parser.rs"]
+mod parser;
+#[allow(dead_code)]
+#[path = "../src/values.rs"]
+mod values;
+
+use std::time::Instant;
+
+use environment::Environment;
+use interpreter::Interpreter;
+use lexer::Lexer;
+use parser::Parser;
+
+// `{n}` is replaced by the number of iterations
+const LOOPS: [(&str, &str); 2] = [
+    (
+        "for over array",
+        "let arr = (0..{n}) as array; let total = 0; for x in arr { total += x; }",
+    ),
+    (
+        "while over array",
+        "let arr = (0..{n}) as array; let total = 0; let i = 0; while i < {n} { total += arr[i]; i += 1; }",
+    ),
+];
+
+fn main() {
+    for (name, script) in LOOPS {
+        for n in [2500, 5000, 10000, 20000] {
+            let (tokens, _) = Lexer::new(script.replace("{n}", &n.to_string())).tokenize();
+            let (program, _) = Parser::new(tokens).generate_ast();
+
+            let started = Instant::now();
+            Interpreter::new(program).interpret(&mut Environment::new(None));
+            let elapsed = started.elapsed();
+
+            println!(
+                "{:<18} {:>6} iterations {:>12.2?} {:>10.2} ns/iteration",
+                name,
+                n,
+                elapsed,
+                elapsed.as_nanos() as f64 / n as f64
+            );
+        }
+    }
+}
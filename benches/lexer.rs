@@ -0,0 +1,39 @@
+// Lexes generated scripts of doubling size and prints the time spent per byte.
+// The lexer is linear when the time per byte stays roughly flat as the input grows.
+//
+// Run with `cargo bench`.
+
+#[allow(dead_code)]
+#[path = "../src/lexer.rs"]
+mod lexer;
+
+use std::time::Instant;
+
+use lexer::Lexer;
+
+const SNIPPET: &str = r#"/// Adds two numbers.
+func add(a: integer, b: integer) -> integer { a + b }
+let name = "world";
+let total = add(0xFF, 1_000) * 2.5e-1 / 3;
+if total >= 10 { print("Hello {name}, total is {total}\n"); } else { print('small'); }
+/* block /* nested */ comment */
+"#;
+
+fn main() {
+    for kib in [128, 256, 512, 1024, 2048] {
+        let source = SNIPPET.repeat(kib * 1024 / SNIPPET.len());
+        let bytes = source.len();
+
+        let started = Instant::now();
+        let tokens = Lexer::new(source).count();
+        let elapsed = started.elapsed();
+
+        println!(
+            "{:>5} KiB {:>9} tokens {:>12.2?} {:>8.2} ns/byte",
+            kib,
+            tokens,
+            elapsed,
+            elapsed.as_nanos() as f64 / bytes as f64
+        );
+    }
+}
@@ -25,3 +25,10 @@ pub fn input(prompt: Vec<RuntimeValue>) -> RuntimeValue {
 pub fn tup(values: Vec<RuntimeValue>) -> RuntimeValue {
     RuntimeValue::Tuple(values)
 }
+
+pub fn doc(arguments: Vec<RuntimeValue>) -> RuntimeValue {
+    match arguments.first() {
+        Some(RuntimeValue::Function(.., Some(doc))) => RuntimeValue::string(doc.clone()),
+        _ => RuntimeValue::Null,
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use crate::{built_in_functions, values::RuntimeValue};
+use crate::{built_in_functions, lexer::Span, parser::ASTNode, values::RuntimeValue};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Environment {
     pub parent: Box<Option<Self>>,
     pub variables: HashMap<String, RuntimeValue>,
@@ -14,6 +14,15 @@ impl Environment {
         let variables = HashMap::new();
         let mut constants = HashMap::new();
 
+        // The globals live in the outermost scope, where every lookup ends up
+        if parent.is_some() {
+            return Environment {
+                parent: Box::new(parent),
+                variables,
+                constants,
+            };
+        }
+
         // Global constants
         constants.insert("true".to_string(), RuntimeValue::Boolean(true));
         constants.insert("false".to_string(), RuntimeValue::Boolean(false));
@@ -26,6 +35,7 @@ impl Environment {
             RuntimeValue::BuiltInFunction(built_in_functions::input, vec![]),
         );
         constants.insert("tup".to_string(), RuntimeValue::BuiltInFunction(built_in_functions::tup, vec![]));
+        constants.insert("doc".to_string(), RuntimeValue::BuiltInFunction(built_in_functions::doc, vec![]));
         
         // Datatype defaults
         constants.insert("null".to_string(), RuntimeValue::Null);
@@ -33,6 +43,17 @@ impl Environment {
         constants.insert("real".to_string(), RuntimeValue::Real(0.0));
         constants.insert("boolean".to_string(), RuntimeValue::Boolean(false));
         constants.insert("tuple".to_string(), RuntimeValue::Tuple(vec![]));
+        constants.insert(
+            "function".to_string(),
+            RuntimeValue::Function(
+                vec![],
+                vec![],
+                Box::new(RuntimeValue::Null),
+                ASTNode::Program(vec![], Span::default()),
+                HashMap::new(),
+                None,
+            ),
+        );
 
 
         Environment {
@@ -42,15 +63,28 @@ impl Environment {
         }
     }
 
-    pub fn lookup(&mut self, variable_name: String) -> &RuntimeValue {
-        if self.variables.contains_key(&variable_name) {
-            &self.variables[&variable_name]
-        } else if self.constants.contains_key(&variable_name) {
-            &self.constants[&variable_name]
-        } else if let Some(parent) = &mut *self.parent {
+    // Opens a scope inside the current one, for a block or a loop iteration
+    pub fn push_scope(&mut self) {
+        let parent = std::mem::take(self);
+        *self = Environment::new(Some(parent));
+    }
+
+    // Closes the innermost scope, keeping what was assigned to the outer scopes inside it
+    pub fn pop_scope(&mut self) {
+        if let Some(parent) = self.parent.take() {
+            *self = parent;
+        }
+    }
+
+    pub fn lookup(&self, variable_name: &str) -> Option<&RuntimeValue> {
+        if let Some(value) = self.variables.get(variable_name) {
+            Some(value)
+        } else if let Some(value) = self.constants.get(variable_name) {
+            Some(value)
+        } else if let Some(parent) = &*self.parent {
             parent.lookup(variable_name)
         } else {
-            panic!("Variable '{}' undefined.", variable_name)
+            None
         }
     }
 
@@ -62,15 +96,29 @@ impl Environment {
         }
     }
 
-    pub fn assign(&mut self, variable_name: String, variable_value: RuntimeValue) {
+    // Variables of every scope except the outermost one, inner scopes hiding outer ones
+    pub fn local_variables(&self) -> HashMap<String, RuntimeValue> {
+        match &*self.parent {
+            Some(parent) => {
+                let mut variables = parent.local_variables();
+                variables.extend(self.variables.clone());
+                variables
+            }
+            None => HashMap::new(),
+        }
+    }
+
+    // Returns false when the variable isn't declared in any scope
+    pub fn assign(&mut self, variable_name: String, variable_value: RuntimeValue) -> bool {
         if let std::collections::hash_map::Entry::Occupied(mut e) =
             self.variables.entry(variable_name.clone())
         {
             e.insert(variable_value);
+            true
         } else if let Some(parent) = &mut *self.parent {
-            parent.assign(variable_name, variable_value);
+            parent.assign(variable_name, variable_value)
         } else {
-            panic!("Variable '{}' undefined", variable_name);
+            false
         }
     }
 }
@@ -1,6 +1,23 @@
 use core::panic;
+use std::collections::HashMap;
 
-use crate::{environment::Environment, parser::ASTNode, values::RuntimeValue};
+use crate::{
+    environment::Environment,
+    lexer::Span,
+    parser::ASTNode,
+    values::{range_contains, range_values, RuntimeValue},
+};
+
+// Evaluates a sub-expression, leaving the enclosing evaluation with the signal when it runs into
+// a `break`, `continue` or `return` inside an if or case value
+macro_rules! try_evaluate {
+    ($interpreter:expr, $node:expr, $environment:expr) => {
+        match $interpreter.evaluate($node, $environment) {
+            value if value.is_signal() => return value,
+            value => value,
+        }
+    };
+}
 
 pub struct Interpreter {
     program: ASTNode,
@@ -14,9 +31,13 @@ impl Interpreter {
     pub fn interpret(&self, environment: &mut Environment) -> RuntimeValue {
         let mut result = RuntimeValue::Null;
 
-        if let ASTNode::Program(body) = &self.program {
+        if let ASTNode::Program(body, _) = &self.program {
             for statement in body.iter() {
                 result = self.evaluate(statement.clone(), environment);
+
+                if result.is_signal() {
+                    break;
+                }
             }
         }
 
@@ -25,66 +46,116 @@ impl Interpreter {
 
     fn evaluate(&self, statement: ASTNode, environment: &mut Environment) -> RuntimeValue {
         match statement {
-            ASTNode::ConditionalExpression(left, operand, right) => {
+            ASTNode::ConditionalExpression(left, operand, right, _) => {
                 self.evaluate_conditional_expression(*left, operand, *right, environment)
             }
-            ASTNode::BinaryExpression(left, operand, right) => self.evaluate_binary_expression(
-                self.evaluate(*left, environment),
-                operand,
-                self.evaluate(*right, environment),
-                environment,
-            ),
-            ASTNode::CallExpression(calle, arguments) => {
-                self.evaluate_call_expression(*calle, arguments, environment)
+            ASTNode::LogicalExpression(left, operand, right, span) => {
+                self.evaluate_logical_expression(*left, operand, *right, span, environment)
+            }
+            ASTNode::BinaryExpression(left, operand, right, _) => {
+                let l = try_evaluate!(self, *left, environment);
+                let r = try_evaluate!(self, *right, environment);
+                self.evaluate_binary_expression(l, operand, r)
             }
-            ASTNode::UnaryExpression(sign, value) => {
-                self.evaluate_unary_expression(sign, *value, environment)
+            ASTNode::CallExpression(calle, arguments, span) => {
+                self.evaluate_call_expression(*calle, arguments, span, environment)
             }
-            ASTNode::AssignmentExpression(variable_name, variable_value) => {
+            ASTNode::UnaryExpression(sign, value, span) => {
+                self.evaluate_unary_expression(sign, *value, span, environment)
+            }
+            ASTNode::AssignmentExpression(variable_name, variable_value, _) => {
                 self.evaluate_assignment_expression(*variable_name, *variable_value, environment)
             }
-            ASTNode::MemberExpression(object, property, dot) => {
-                self.evaluate_member_expression(*object, *property, dot, environment)
+            ASTNode::CompoundAssignmentExpression(target, operand, value, _) => {
+                self.evaluate_compound_assignment_expression(*target, operand, *value, environment)
+            }
+            ASTNode::MemberExpression(object, property, dot, span) => {
+                self.evaluate_member_expression(*object, *property, dot, span, environment)
             }
-            ASTNode::ConversionExpression(left, right) => {
+            ASTNode::ConversionExpression(left, right, _) => {
                 self.evaluate_conversion_expression(*left, *right, environment)
             }
+            ASTNode::RangeExpression(start, end, inclusive, span) => {
+                self.evaluate_range_expression(*start, *end, inclusive, span, environment)
+            }
 
-            ASTNode::StringLiteral(value) => RuntimeValue::string(value),
-            ASTNode::IntegerLiteral(value) => RuntimeValue::Integer(value),
-            ASTNode::NullLiteral => RuntimeValue::Null,
-            ASTNode::Identifier(variable_name) => environment.lookup(variable_name).clone(),
-            ASTNode::ArrayLiteral(values) => RuntimeValue::array(
-                values
-                    .iter()
-                    .map(|v| self.evaluate(v.clone(), environment))
-                    .collect(),
-            ),
-            ASTNode::RealLiteral(value) => RuntimeValue::Real(value),
+            ASTNode::StringLiteral(value, _) => RuntimeValue::string(value),
+            ASTNode::IntegerLiteral(value, _) => RuntimeValue::Integer(value),
+            ASTNode::NullLiteral(_) => RuntimeValue::Null,
+            ASTNode::Identifier(variable_name, span) => match environment.lookup(&variable_name) {
+                Some(value) => value.clone(),
+                None => panic!("Variable '{}' undefined at {}", variable_name, span),
+            },
+            ASTNode::ArrayLiteral(values, _) => {
+                let mut elements = Vec::new();
+                for value in values {
+                    elements.push(try_evaluate!(self, value, environment));
+                }
+                RuntimeValue::array(elements)
+            }
+            ASTNode::TupleLiteral(values, _) => {
+                let mut elements = Vec::new();
+                for value in values {
+                    elements.push(try_evaluate!(self, value, environment));
+                }
+                RuntimeValue::Tuple(elements)
+            }
+            ASTNode::RealLiteral(value, _) => RuntimeValue::Real(value),
+            ASTNode::ObjectLiteral(properties, _) => {
+                let mut object = HashMap::new();
+                for (key, value) in properties {
+                    object.insert(key, try_evaluate!(self, value, environment));
+                }
+                RuntimeValue::object(object)
+            }
+            ASTNode::InterpolatedString(parts, _) => {
+                self.evaluate_interpolated_string(parts, environment)
+            }
 
-            ASTNode::VariableDeclaration(variable_name, variable_value) => {
+            ASTNode::ExpressionStatement(expression, _) => {
+                try_evaluate!(self, *expression, environment);
+                RuntimeValue::Null
+            }
+            ASTNode::VariableDeclaration(variable_name, variable_value, _) => {
                 self.evaluate_variable_declaration(*variable_name, *variable_value, environment)
             }
-            ASTNode::FunctionDeclaration(name, parameters, parameter_types, return_type, body) => {
-                self.evaluate_function_declaration(*name, parameters, parameter_types, *return_type, *body, environment)
+            ASTNode::FunctionDeclaration(name, parameters, parameter_types, return_type, body, doc, _) => {
+                self.evaluate_function_declaration(*name, parameters, parameter_types, *return_type, *body, doc, environment)
             }
-            ASTNode::IfStatement(condition, body, else_body) => {
+            ASTNode::FunctionExpression(parameters, parameter_types, return_type, body, _) => {
+                self.evaluate_function_expression(parameters, parameter_types, *return_type, *body, environment)
+            }
+            ASTNode::IfStatement(condition, body, else_body, _) => {
                 self.evaluate_if_statement(*condition, *body, *else_body, environment)
             }
-            ASTNode::CaseStatement(value, cases) => {
+            ASTNode::CaseStatement(value, cases, _) => {
                 self.evaluate_case_statement(*value, cases, environment)
             }
+            ASTNode::WhileStatement(condition, body, _) => {
+                self.evaluate_while_statement(*condition, *body, environment)
+            }
+            ASTNode::ForStatement(variables, iterable, body, _) => {
+                self.evaluate_for_statement(variables, *iterable, *body, environment)
+            }
+            ASTNode::BreakStatement(_) => RuntimeValue::Break,
+            ASTNode::ContinueStatement(_) => RuntimeValue::Continue,
+            ASTNode::ReturnStatement(value, _) => {
+                RuntimeValue::Return(Box::new(try_evaluate!(self, *value, environment)))
+            }
 
-            ASTNode::Program(_) => {
+            ASTNode::Program(..) => {
                 let scope_interpreter = Interpreter::new(statement);
-                let mut scope_environment = Environment::new(Some(environment.clone()));
 
-                scope_interpreter.interpret(&mut scope_environment)
+                environment.push_scope();
+                let result = scope_interpreter.interpret(environment);
+                environment.pop_scope();
+                result
             }
             _ => RuntimeValue::Null,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn evaluate_function_declaration(
         &self,
         name: ASTNode,
@@ -92,6 +163,7 @@ impl Interpreter {
         parameter_types: Vec<ASTNode>,
         return_type: ASTNode,
         body: ASTNode,
+        doc: Option<String>,
         environment: &mut Environment,
     ) -> RuntimeValue {
         let mut para_types = Vec::new();
@@ -102,57 +174,173 @@ impl Interpreter {
         }
 
         match name {
-            ASTNode::Identifier(function_name) => {
-                let function = RuntimeValue::Function(parameters, para_types, Box::new(return_type), body);
-                environment.declare_variable(function_name, function.clone());
-                function
+            ASTNode::Identifier(function_name, _) => {
+                // Like anonymous functions it keeps a copy of the local variables around it, and of
+                // itself so it can still call itself once returned from the function declaring it
+                let mut captured = environment.local_variables();
+                let function = RuntimeValue::Function(
+                    parameters.clone(),
+                    para_types.clone(),
+                    Box::new(return_type.clone()),
+                    body.clone(),
+                    captured.clone(),
+                    doc.clone(),
+                );
+                captured.insert(function_name.clone(), function);
+
+                let function = RuntimeValue::Function(
+                    parameters,
+                    para_types,
+                    Box::new(return_type),
+                    body,
+                    captured,
+                    doc,
+                );
+                environment.declare_variable(function_name, function);
+                RuntimeValue::Null
             }
-            _ => panic!(),
+            _ => panic!("Expected a function name at {}", name.span()),
         }
     }
 
+    fn evaluate_function_expression(
+        &self,
+        parameters: Vec<ASTNode>,
+        parameter_types: Vec<ASTNode>,
+        return_type: ASTNode,
+        body: ASTNode,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let return_type = self.evaluate(return_type, environment);
+        let para_types = parameter_types
+            .into_iter()
+            .map(|parameter_type| self.evaluate(parameter_type, environment))
+            .collect();
+
+        // Anonymous functions keep a copy of the local variables around them, so they still work
+        // after being returned from the function that made them
+        RuntimeValue::Function(
+            parameters,
+            para_types,
+            Box::new(return_type),
+            body,
+            environment.local_variables(),
+            None,
+        )
+    }
+
+    fn evaluate_interpolated_string(
+        &self,
+        parts: Vec<ASTNode>,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let mut result = String::new();
+
+        for part in parts {
+            let span = part.span();
+            let value = try_evaluate!(self, part, environment);
+
+            if let RuntimeValue::String(s, _) = Interpreter::convert(value, "string", span) {
+                result.push_str(&s);
+            }
+        }
+
+        RuntimeValue::string(result)
+    }
+
     fn evaluate_conversion_expression(
         &self,
         left: ASTNode,
         right: ASTNode,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let l = self.evaluate(left, environment);
+        let l = try_evaluate!(self, left, environment);
 
         match right {
-            ASTNode::Identifier(kind) => match kind.as_str() {
-                "integer" => match l {
-                    RuntimeValue::Integer(v) => RuntimeValue::Integer(v),
-                    RuntimeValue::Real(v) => RuntimeValue::Integer(v as i64),
-                    RuntimeValue::Boolean(v) => RuntimeValue::Integer(if v { 1 } else { 0 }),
-                    RuntimeValue::String(v, _) => RuntimeValue::Integer(v.parse::<i64>().unwrap()),
-                    _ => RuntimeValue::Null,
-                },
-                "real" => match l {
-                    RuntimeValue::Integer(v) => RuntimeValue::Real(v as f64),
-                    RuntimeValue::Real(v) => RuntimeValue::Real(v),
-                    RuntimeValue::String(v, _) => RuntimeValue::Real(v.parse::<f64>().unwrap()),
-                    _ => RuntimeValue::Null,
-                },
-                "boolean" => match l {
-                    RuntimeValue::Integer(v) => RuntimeValue::Boolean(v != 0),
-                    RuntimeValue::Real(v) => RuntimeValue::Boolean(v != 0.0),
-                    RuntimeValue::Boolean(v) => RuntimeValue::Boolean(v),
-                    RuntimeValue::String(v, _) => RuntimeValue::Boolean(!v.is_empty()),
-                    RuntimeValue::Array(v, _) => RuntimeValue::Boolean(!v.is_empty()),
-                    RuntimeValue::Object(v, _) => RuntimeValue::Boolean(!v.is_empty()),
-                    RuntimeValue::Tuple(v) => RuntimeValue::Boolean(!v.is_empty()),
-                    _ => RuntimeValue::Null,
+            ASTNode::Identifier(kind, span) => Interpreter::convert(l, &kind, span),
+            _ => RuntimeValue::Null,
+        }
+    }
+
+    fn evaluate_range_expression(
+        &self,
+        start: ASTNode,
+        end: ASTNode,
+        inclusive: bool,
+        span: Span,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let start = try_evaluate!(self, start, environment);
+        let end = try_evaluate!(self, end, environment);
+
+        match (&start, &end) {
+            (RuntimeValue::Integer(s), RuntimeValue::Integer(e)) => {
+                RuntimeValue::range(*s, *e, 1, inclusive)
+            }
+            _ => panic!("Range bounds must be integers, found '{}' and '{}' at {}", start, end, span),
+        }
+    }
+
+    // Converts a value to the datatype named `kind`, used by `as`/`to` and string interpolation
+    fn convert(l: RuntimeValue, kind: &str, span: Span) -> RuntimeValue {
+        match kind {
+            "integer" => match l {
+                RuntimeValue::Integer(v) => RuntimeValue::Integer(v),
+                RuntimeValue::Real(v) => RuntimeValue::Integer(v as i64),
+                RuntimeValue::Boolean(v) => RuntimeValue::Integer(if v { 1 } else { 0 }),
+                RuntimeValue::String(v, _) => match v.parse::<i64>() {
+                    Ok(v) => RuntimeValue::Integer(v),
+                    Err(_) => panic!("Cannot convert '{}' to integer at {}", v, span),
                 },
-                "string" => match l {
-                    RuntimeValue::Integer(v) => RuntimeValue::string(v.to_string()),
-                    RuntimeValue::Real(v) => RuntimeValue::string(v.to_string()),
-                    RuntimeValue::Boolean(v) => RuntimeValue::string(v.to_string()),
-                    RuntimeValue::String(v, _) => RuntimeValue::string(v),
-                    _ => RuntimeValue::Null,
+                _ => RuntimeValue::Null,
+            },
+            "real" => match l {
+                RuntimeValue::Integer(v) => RuntimeValue::Real(v as f64),
+                RuntimeValue::Real(v) => RuntimeValue::Real(v),
+                RuntimeValue::String(v, _) => match v.parse::<f64>() {
+                    Ok(v) => RuntimeValue::Real(v),
+                    Err(_) => panic!("Cannot convert '{}' to real at {}", v, span),
                 },
                 _ => RuntimeValue::Null,
             },
+            "boolean" => match l {
+                RuntimeValue::Integer(v) => RuntimeValue::Boolean(v != 0),
+                RuntimeValue::Real(v) => RuntimeValue::Boolean(v != 0.0),
+                RuntimeValue::Boolean(v) => RuntimeValue::Boolean(v),
+                RuntimeValue::String(v, _) => RuntimeValue::Boolean(!v.is_empty()),
+                RuntimeValue::Array(v, _) => RuntimeValue::Boolean(!v.is_empty()),
+                RuntimeValue::Object(v, _) => RuntimeValue::Boolean(!v.is_empty()),
+                RuntimeValue::Tuple(v) => RuntimeValue::Boolean(!v.is_empty()),
+                RuntimeValue::Range(start, end, step, inclusive, _) => {
+                    RuntimeValue::Boolean(range_values(start, end, step, inclusive).next().is_some())
+                }
+                _ => RuntimeValue::Null,
+            },
+            "string" => match l {
+                RuntimeValue::Integer(v) => RuntimeValue::string(v.to_string()),
+                RuntimeValue::Real(v) => RuntimeValue::string(v.to_string()),
+                RuntimeValue::Boolean(v) => RuntimeValue::string(v.to_string()),
+                RuntimeValue::String(v, _) => RuntimeValue::string(v),
+                // Written the same way `print` writes them
+                RuntimeValue::Null
+                | RuntimeValue::Array(..)
+                | RuntimeValue::Object(..)
+                | RuntimeValue::Tuple(..)
+                | RuntimeValue::Range(..) => RuntimeValue::string(l.to_string()),
+                _ => panic!("Cannot convert a function to string at {}", span),
+            },
+            "array" => match l {
+                RuntimeValue::Array(v, _) | RuntimeValue::Tuple(v) => RuntimeValue::array(v),
+                RuntimeValue::String(v, _) => RuntimeValue::array(
+                    v.chars().map(|c| RuntimeValue::string(c.to_string())).collect(),
+                ),
+                RuntimeValue::Range(start, end, step, inclusive, _) => RuntimeValue::array(
+                    range_values(start, end, step, inclusive)
+                        .map(RuntimeValue::Integer)
+                        .collect(),
+                ),
+                _ => RuntimeValue::Null,
+            },
             _ => RuntimeValue::Null,
         }
     }
@@ -163,11 +351,11 @@ impl Interpreter {
         cases: Vec<ASTNode>,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let evaluated_value = self.evaluate(value, environment);
+        let evaluated_value = try_evaluate!(self, value, environment);
 
         for case in cases {
-            if let ASTNode::Case(c, body) = case {
-                let evaluated_case = self.evaluate(*c, environment);
+            if let ASTNode::Case(c, body, _) = case {
+                let evaluated_case = try_evaluate!(self, *c, environment);
                 if (evaluated_case == RuntimeValue::Null) || (evaluated_value == evaluated_case) {
                     return self.evaluate(*body, environment);
                 };
@@ -177,6 +365,92 @@ impl Interpreter {
         RuntimeValue::Null
     }
 
+    fn evaluate_while_statement(
+        &self,
+        condition: ASTNode,
+        body: ASTNode,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        while let RuntimeValue::Boolean(true) = try_evaluate!(self, condition.clone(), environment) {
+            match self.evaluate(body.clone(), environment) {
+                RuntimeValue::Break => break,
+                result @ RuntimeValue::Return(_) => return result,
+                _ => {}
+            }
+        }
+
+        RuntimeValue::Null
+    }
+
+    fn evaluate_for_statement(
+        &self,
+        variables: Vec<ASTNode>,
+        iterable: ASTNode,
+        body: ASTNode,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let span = iterable.span();
+        let value = try_evaluate!(self, iterable, environment);
+
+        // (index, element) pairs, or (key, value) pairs for objects, produced one at a time
+        let pairs: Box<dyn Iterator<Item = (RuntimeValue, RuntimeValue)>> = match &value {
+            RuntimeValue::Array(elements, _) | RuntimeValue::Tuple(elements) => Box::new(
+                elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| (RuntimeValue::Integer(i as i64), element.clone())),
+            ),
+            RuntimeValue::String(s, _) => Box::new(
+                s.chars()
+                    .enumerate()
+                    .map(|(i, c)| (RuntimeValue::Integer(i as i64), RuntimeValue::string(c.to_string()))),
+            ),
+            RuntimeValue::Object(properties, _) => {
+                let mut keys: Vec<&String> = properties.keys().collect();
+                keys.sort();
+                Box::new(
+                    keys.into_iter()
+                        .map(|key| (RuntimeValue::string(key.clone()), properties[key].clone())),
+                )
+            }
+            RuntimeValue::Range(start, end, step, inclusive, _) => Box::new(
+                range_values(*start, *end, *step, *inclusive)
+                    .enumerate()
+                    .map(|(i, v)| (RuntimeValue::Integer(i as i64), RuntimeValue::Integer(v))),
+            ),
+            _ => panic!("Cannot iterate over '{}' at {}", value, span),
+        };
+        let is_object = matches!(value, RuntimeValue::Object(..));
+
+        for (first, second) in pairs {
+            // A single loop variable gets the element, or the key for objects
+            let values = match (variables.len(), is_object) {
+                (1, true) => vec![first],
+                (1, false) => vec![second],
+                _ => vec![first, second],
+            };
+
+            // Every iteration gets a fresh scope for its loop variables
+            environment.push_scope();
+            for (variable, value) in variables.iter().zip(values) {
+                if let ASTNode::Identifier(name, _) = variable {
+                    environment.declare_variable(name.clone(), value);
+                }
+            }
+
+            let result = self.evaluate(body.clone(), environment);
+            environment.pop_scope();
+
+            match result {
+                RuntimeValue::Break => break,
+                RuntimeValue::Return(_) => return result,
+                _ => {}
+            }
+        }
+
+        RuntimeValue::Null
+    }
+
     fn evaluate_if_statement(
         &self,
         condition: ASTNode,
@@ -184,7 +458,7 @@ impl Interpreter {
         else_body: ASTNode,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let evaluated_condition = self.evaluate(condition, environment);
+        let evaluated_condition = try_evaluate!(self, condition, environment);
 
         match evaluated_condition {
             RuntimeValue::Boolean(b) => {
@@ -205,8 +479,8 @@ impl Interpreter {
         right: ASTNode,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let l = self.evaluate(left, environment);
-        let r = self.evaluate(right, environment);
+        let l = try_evaluate!(self, left, environment);
+        let r = try_evaluate!(self, right, environment);
 
         match (operand.as_str(), l, r) {
             ("<", RuntimeValue::Integer(lhs), RuntimeValue::Real(rhs)) => {
@@ -365,57 +639,113 @@ impl Interpreter {
                 RuntimeValue::Boolean(lhs != rhs)
             }
 
+            ("in", RuntimeValue::Integer(lhs), RuntimeValue::Range(start, end, step, inclusive, _)) => {
+                RuntimeValue::Boolean(range_contains(start, end, step, inclusive, lhs))
+            }
+            ("in", lhs, RuntimeValue::Array(rhs, _) | RuntimeValue::Tuple(rhs)) => {
+                RuntimeValue::Boolean(rhs.contains(&lhs))
+            }
+            ("in", RuntimeValue::String(lhs, _), RuntimeValue::String(rhs, _)) => {
+                RuntimeValue::Boolean(rhs.contains(&lhs))
+            }
+            ("in", RuntimeValue::String(lhs, _), RuntimeValue::Object(rhs, _)) => {
+                RuntimeValue::Boolean(rhs.contains_key(&lhs))
+            }
+
             _ => RuntimeValue::Boolean(false),
         }
     }
 
+    fn evaluate_logical_expression(
+        &self,
+        left: ASTNode,
+        operand: String,
+        right: ASTNode,
+        span: Span,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let l = try_evaluate!(self, left, environment);
+
+        // `&` and `|` skip the right side when the left side already decides the result
+        match (operand.as_str(), &l) {
+            ("&", RuntimeValue::Boolean(false)) => return RuntimeValue::Boolean(false),
+            ("|", RuntimeValue::Boolean(true)) => return RuntimeValue::Boolean(true),
+            _ => {}
+        }
+        let r = try_evaluate!(self, right, environment);
+
+        match (operand.as_str(), &l, &r) {
+            ("&", RuntimeValue::Boolean(lhs), RuntimeValue::Boolean(rhs)) => RuntimeValue::Boolean(*lhs && *rhs),
+            ("|", RuntimeValue::Boolean(lhs), RuntimeValue::Boolean(rhs)) => RuntimeValue::Boolean(*lhs || *rhs),
+            ("^", RuntimeValue::Boolean(lhs), RuntimeValue::Boolean(rhs)) => RuntimeValue::Boolean(lhs ^ rhs),
+            _ => panic!("Cannot apply '{}' to '{}' and '{}' at {}", operand, l, r, span),
+        }
+    }
+
     fn evaluate_call_expression(
         &self,
         calle: ASTNode,
         arguments: Vec<ASTNode>,
+        span: Span,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let caller = self.evaluate(calle, environment);
+        let caller = try_evaluate!(self, calle, environment);
         let mut args = Vec::new();
 
         for arg in arguments {
-            args.push(self.evaluate(arg, environment));
+            args.push(try_evaluate!(self, arg, environment));
         }
 
         match caller {
             RuntimeValue::BuiltInFunction(call, _) => call(args),
-            RuntimeValue::Method(call, object, _) => call(*object, args),
-            RuntimeValue::Function(parameters, parameter_types, return_type, body) => {
+            // Methods report what went wrong and the call site supplies the location
+            RuntimeValue::Method(call, object, _) => {
+                call(*object, args).unwrap_or_else(|message| panic!("{} at {}", message, span))
+            }
+            RuntimeValue::Function(parameters, parameter_types, return_type, body, captured, _) => {
                 let scope_interpreter = Interpreter::new(body);
                 let mut scope_environment = Environment::new(Some(environment.clone()));
 
+                // Captured variables only stand in for the ones that are out of scope at the call,
+                // a variable that is still in scope is read as it is now
+                for (name, value) in captured {
+                    if environment.lookup(&name).is_none() {
+                        scope_environment.variables.insert(name, value);
+                    }
+                }
+
                 if parameters.len() == args.len() && args.len() == parameter_types.len() {
                     for ((parameter, arg), expected_type) in parameters.iter().zip(args).zip(parameter_types) {
-                        if let ASTNode::Identifier(variable_name) = parameter {
+                        if let ASTNode::Identifier(variable_name, _) = parameter {
                             
-                            match (arg.clone(), expected_type.clone()) {
-                                (ref arg, _) if std::mem::discriminant(arg) == std::mem::discriminant(&expected_type) => {
-                                    scope_environment.declare_variable(variable_name.to_string(), arg.clone())
-                                },
-                                _ => panic!("Type Error: Expected type '{:?}' but found type '{:?}'", expected_type, arg)
+                            if Interpreter::has_type(&arg, &expected_type) {
+                                scope_environment.declare_variable(variable_name.to_string(), arg)
+                            } else {
+                                panic!("Type Error: Expected type '{:?}' but found type '{:?}' at {}", expected_type, arg, span)
                             }
                         } else {
-                            panic!()
+                            panic!("Expected a parameter name at {}", parameter.span())
                         }
                     }
 
-                    let result = scope_interpreter.interpret(&mut scope_environment);
+                    // A `return` anywhere in the body ends the call with its value
+                    let result = match scope_interpreter.interpret(&mut scope_environment) {
+                        RuntimeValue::Return(value) => *value,
+                        result => result,
+                    };
 
-                    match (result.clone(), return_type.clone()) {
-                        (ref result_value, _) if std::mem::discriminant(result_value) == std::mem::discriminant(&return_type) => result,
-                        _ => panic!("Type Error: Expected type '{:?}' but found type '{:?}'", return_type, result),
+                    if Interpreter::has_type(&result, &return_type) {
+                        result
+                    } else {
+                        panic!("Type Error: Expected type '{:?}' but found type '{:?}' at {}", return_type, result, span)
                     }
 
                 } else {
                     panic!(
-                        "Wrong number of arguments provided. Expected {} but got {}",
+                        "Wrong number of arguments provided. Expected {} but got {} at {}",
                         parameters.len(),
-                        args.len()
+                        args.len(),
+                        span
                     );
                 }
             }
@@ -423,37 +753,65 @@ impl Interpreter {
         }
     }
 
+    // Whether `value` is of the type whose default value is `expected`. Built-in functions and
+    // methods are functions too.
+    fn has_type(value: &RuntimeValue, expected: &RuntimeValue) -> bool {
+        match (value, expected) {
+            (RuntimeValue::BuiltInFunction(..) | RuntimeValue::Method(..), RuntimeValue::Function(..)) => true,
+            _ => std::mem::discriminant(value) == std::mem::discriminant(expected),
+        }
+    }
+
     fn evaluate_member_expression(
         &self,
         object: ASTNode,
         property: ASTNode,
         dot: bool,
+        span: Span,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let obj = self.evaluate(object, environment);
+        // A variable is read in place once the property is known, so indexing it doesn't copy the
+        // whole array or string
+        let (variable, obj) = match object {
+            ASTNode::Identifier(name, name_span) => (Some((name, name_span)), RuntimeValue::Null),
+            object => (None, try_evaluate!(self, object, environment)),
+        };
         let prop = if dot {
             match property {
-                ASTNode::Identifier(s) => RuntimeValue::string(s),
+                ASTNode::Identifier(s, _) => RuntimeValue::string(s),
                 _ => RuntimeValue::Null,
             }
         } else {
-            self.evaluate(property, environment)
+            try_evaluate!(self, property, environment)
         };
 
-        match (&obj, prop) {
+        match variable {
+            Some((name, name_span)) => match environment.lookup(&name) {
+                Some(obj) => Interpreter::get_member(obj, prop, span),
+                None => panic!("Variable '{}' undefined at {}", name, name_span),
+            },
+            None => Interpreter::get_member(&obj, prop, span),
+        }
+    }
+
+    // The property, method, element or slice `prop` of `obj`
+    fn get_member(obj: &RuntimeValue, prop: RuntimeValue, span: Span) -> RuntimeValue {
+        match (obj, prop) {
             // Properties and Methods
             (RuntimeValue::Object(o, methods), RuntimeValue::String(p, _)) => methods
                 .get(&p)
                 .map(|meth| RuntimeValue::Method(*meth, Box::new(obj.clone()), vec![]))
                 .or_else(|| o.get(&p).cloned())
-                .expect("Property not found on object"),
+                .unwrap_or_else(|| panic!("Property '{}' not found on object at {}", p, span)),
             (
-                RuntimeValue::Array(_, methods) | RuntimeValue::String(_, methods),
+                RuntimeValue::Array(_, methods)
+                | RuntimeValue::String(_, methods)
+                | RuntimeValue::Range(.., methods),
                 RuntimeValue::String(method, _),
             ) => methods
                 .get(&method)
                 .map(|meth| RuntimeValue::Method(*meth, Box::new(obj.clone()), vec![]))
-                .expect("Method not found"),
+                .unwrap_or_else(|| panic!("Method '{}' not found at {}", method, span)),
 
             // Indexing
             (RuntimeValue::String(s, _), RuntimeValue::Integer(i)) => RuntimeValue::string(
@@ -464,6 +822,23 @@ impl Interpreter {
             (RuntimeValue::Array(a, _), RuntimeValue::Integer(i)) => {
                 a.get(i as usize).cloned().unwrap_or(RuntimeValue::Null)
             }
+
+            // Slicing, indexes outside of the array or string are left out
+            (RuntimeValue::Array(a, _), RuntimeValue::Range(start, end, step, inclusive, _)) => {
+                RuntimeValue::array(
+                    slice_indices(start, end, step, inclusive, a.len())
+                        .map(|i| a[i].clone())
+                        .collect(),
+                )
+            }
+            (RuntimeValue::String(s, _), RuntimeValue::Range(start, end, step, inclusive, _)) => {
+                let chars: Vec<char> = s.chars().collect();
+                RuntimeValue::string(
+                    slice_indices(start, end, step, inclusive, chars.len())
+                        .map(|i| chars[i])
+                        .collect(),
+                )
+            }
             _ => RuntimeValue::Null,
         }
     }
@@ -474,16 +849,65 @@ impl Interpreter {
         variable_value: ASTNode,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let variable_name;
+        let value = try_evaluate!(self, variable_value, environment);
+        self.declare(variable, value, environment);
+        RuntimeValue::Null
+    }
 
-        match (variable, variable_value.clone()) {
-            (ASTNode::Identifier(n), _) => {
-                variable_name = n;
-                let value = self.evaluate(variable_value, environment);
-                environment.declare_variable(variable_name, value);
-                RuntimeValue::Null
+    // Declares the variables named in a `let` target, unpacking tuples, arrays and objects into
+    // their patterns. Elements and properties that are missing are declared as null.
+    fn declare(&self, target: ASTNode, value: RuntimeValue, environment: &mut Environment) {
+        match target {
+            ASTNode::Identifier(name, _) => environment.declare_variable(name, value),
+            ASTNode::ArrayPattern(patterns, rest, span) => {
+                let mut values = match value {
+                    RuntimeValue::Array(values, _) | RuntimeValue::Tuple(values) => values.into_iter(),
+                    value => panic!("Cannot unpack '{}' into an array pattern at {}", value, span),
+                };
+
+                for pattern in patterns {
+                    let value = values.next().unwrap_or(RuntimeValue::Null);
+                    self.declare(pattern, value, environment);
+                }
+                if let Some(rest) = rest {
+                    self.declare(*rest, RuntimeValue::array(values.collect()), environment);
+                }
             }
-            _ => panic!(),
+            ASTNode::ObjectPattern(properties, rest, span) => {
+                let mut values = match value {
+                    RuntimeValue::Object(values, _) => values,
+                    value => panic!("Cannot unpack '{}' into an object pattern at {}", value, span),
+                };
+
+                for (key, pattern) in properties {
+                    let value = values.remove(&key).unwrap_or(RuntimeValue::Null);
+                    self.declare(pattern, value, environment);
+                }
+                if let Some(rest) = rest {
+                    self.declare(*rest, RuntimeValue::object(values), environment);
+                }
+            }
+            ASTNode::TupleLiteral(targets, span) => {
+                let values = Interpreter::unpack_tuple(value, targets.len(), span);
+                for (target, value) in targets.into_iter().zip(values) {
+                    self.declare(target, value, environment);
+                }
+            }
+            target => panic!("Expected an identifier at {}", target.span()),
+        }
+    }
+
+    // Elements of a tuple that is unpacked into `count` targets
+    fn unpack_tuple(value: RuntimeValue, count: usize, span: Span) -> Vec<RuntimeValue> {
+        match value {
+            RuntimeValue::Tuple(values) if values.len() == count => values,
+            RuntimeValue::Tuple(values) => panic!(
+                "Expected a tuple of {} values but found {} values at {}",
+                count,
+                values.len(),
+                span
+            ),
+            value => panic!("Cannot unpack '{}' into {} variables at {}", value, count, span),
         }
     }
 
@@ -493,13 +917,123 @@ impl Interpreter {
         variable_value: ASTNode,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        match (variable_name, variable_value.clone()) {
-            (ASTNode::Identifier(name), _) => {
-                let value = self.evaluate(variable_value, environment);
-                environment.assign(name, value);
-                RuntimeValue::Null
+        let target = match self.resolve_target(variable_name, environment) {
+            Ok(target) => target,
+            Err(signal) => return *signal,
+        };
+        let value = try_evaluate!(self, variable_value, environment);
+        self.assign(target, value, environment);
+        RuntimeValue::Null
+    }
+
+    fn evaluate_compound_assignment_expression(
+        &self,
+        target: ASTNode,
+        operand: String,
+        value: ASTNode,
+        environment: &mut Environment,
+    ) -> RuntimeValue {
+        let target = match self.resolve_target(target, environment) {
+            Ok(target) => target,
+            Err(signal) => return *signal,
+        };
+        let current = try_evaluate!(self, target.clone(), environment);
+        let value = try_evaluate!(self, value, environment);
+
+        let result = self.evaluate_binary_expression(current, operand, value);
+        self.assign(target, result, environment);
+        RuntimeValue::Null
+    }
+
+    // Evaluates the indexes in an assignment target up front and puts their values in their place,
+    // so a target that is both read and written only runs them once. A `break`, `continue` or
+    // `return` hit in an index is returned as the error.
+    fn resolve_target(&self, target: ASTNode, environment: &mut Environment) -> Result<ASTNode, Box<RuntimeValue>> {
+        Ok(match target {
+            ASTNode::MemberExpression(object, property, false, span) => {
+                let object = self.resolve_target(*object, environment)?;
+                let property_span = property.span();
+                let property = match self.evaluate(*property, environment) {
+                    RuntimeValue::Integer(i) => ASTNode::IntegerLiteral(i, property_span),
+                    RuntimeValue::String(s, _) => ASTNode::StringLiteral(s, property_span),
+                    signal if signal.is_signal() => return Err(Box::new(signal)),
+                    key => panic!("Cannot index with '{}' at {}", key, property_span),
+                };
+                ASTNode::MemberExpression(Box::new(object), Box::new(property), false, span)
+            }
+            ASTNode::MemberExpression(object, property, true, span) => {
+                let object = self.resolve_target(*object, environment)?;
+                ASTNode::MemberExpression(Box::new(object), property, true, span)
             }
-            _ => panic!("Expected Identifier"),
+            ASTNode::TupleLiteral(targets, span) => {
+                let targets = targets
+                    .into_iter()
+                    .map(|target| self.resolve_target(target, environment))
+                    .collect::<Result<_, _>>()?;
+                ASTNode::TupleLiteral(targets, span)
+            }
+            target => target,
+        })
+    }
+
+    // Stores `value` in an assignment target, which is either a variable or an element or property
+    // of one. The changed array, object or string is then stored back in its own target, up to the
+    // variable that owns it.
+    fn assign(&self, target: ASTNode, value: RuntimeValue, environment: &mut Environment) {
+        match target {
+            ASTNode::Identifier(name, span) => {
+                if !environment.assign(name.clone(), value) {
+                    panic!("Variable '{}' undefined at {}", name, span);
+                }
+            }
+            ASTNode::MemberExpression(object, property, dot, span) => {
+                let container = self.evaluate((*object).clone(), environment);
+                let key = match (dot, *property) {
+                    (true, ASTNode::Identifier(name, _)) => RuntimeValue::string(name),
+                    (true, property) => panic!("Expected a property name at {}", property.span()),
+                    (false, property) => self.evaluate(property, environment),
+                };
+
+                let container = Interpreter::set_member(container, key, value, span);
+                self.assign(*object, container, environment);
+            }
+            ASTNode::TupleLiteral(targets, span) => {
+                let values = Interpreter::unpack_tuple(value, targets.len(), span);
+                for (target, value) in targets.into_iter().zip(values) {
+                    self.assign(target, value, environment);
+                }
+            }
+            target => panic!("Cannot assign to this expression at {}", target.span()),
+        }
+    }
+
+    // Returns `container` with the element or property at `key` replaced by `value`
+    fn set_member(container: RuntimeValue, key: RuntimeValue, value: RuntimeValue, span: Span) -> RuntimeValue {
+        match (container, key) {
+            (RuntimeValue::Array(mut a, methods), RuntimeValue::Integer(i)) => {
+                match usize::try_from(i).ok().and_then(|i| a.get_mut(i)) {
+                    Some(element) => *element = value,
+                    None => panic!("Index {} out of range for an array of length {} at {}", i, a.len(), span),
+                }
+                RuntimeValue::Array(a, methods)
+            }
+            (RuntimeValue::Object(mut o, methods), RuntimeValue::String(key, _)) => {
+                o.insert(key, value);
+                RuntimeValue::Object(o, methods)
+            }
+            (RuntimeValue::String(s, _), RuntimeValue::Integer(i)) => {
+                let mut chars: Vec<String> = s.chars().map(|c| c.to_string()).collect();
+                let replacement = match value {
+                    RuntimeValue::String(v, _) => v,
+                    value => panic!("Expected a string to put in a string, found '{}' at {}", value, span),
+                };
+                match usize::try_from(i).ok().and_then(|i| chars.get_mut(i)) {
+                    Some(c) => *c = replacement,
+                    None => panic!("Index {} out of range for a string of length {} at {}", i, chars.len(), span),
+                }
+                RuntimeValue::string(chars.concat())
+            }
+            (container, key) => panic!("Cannot assign to '{}' of '{}' at {}", key, container, span),
         }
     }
 
@@ -507,15 +1041,16 @@ impl Interpreter {
         &self,
         sign: String,
         value: ASTNode,
+        span: Span,
         environment: &mut Environment,
     ) -> RuntimeValue {
-        let value = self.evaluate(value, environment);
+        let value = try_evaluate!(self, value, environment);
         match sign.as_str() {
             "-" | "!" => match value {
                 RuntimeValue::Boolean(b) => RuntimeValue::Boolean(!b),
                 RuntimeValue::Integer(i) => RuntimeValue::Integer(-i),
                 RuntimeValue::Real(f) => RuntimeValue::Real(-f),
-                _ => panic!(""),
+                _ => panic!("Cannot apply '{}' to '{}' at {}", sign, value, span),
             },
             _ => value,
         }
@@ -526,7 +1061,6 @@ impl Interpreter {
         left: RuntimeValue,
         operand: String,
         right: RuntimeValue,
-        environment: &mut Environment,
     ) -> RuntimeValue {
 
         match (operand.as_str(), left, right) {
@@ -607,7 +1141,7 @@ impl Interpreter {
 
             // Tuple : Tuple
             (op, RuntimeValue::Tuple(lhs), RuntimeValue::Tuple(rhs)) => {
-                let res = lhs.iter().zip(rhs).map(|(l, r)| self.evaluate_binary_expression(l.clone(), op.to_string(), r, environment)).collect::<Vec<RuntimeValue>>();
+                let res = lhs.iter().zip(rhs).map(|(l, r)| self.evaluate_binary_expression(l.clone(), op.to_string(), r)).collect::<Vec<RuntimeValue>>();
                 RuntimeValue::Tuple(res)
             },
 
@@ -616,3 +1150,23 @@ impl Interpreter {
         }
     }
 }
+
+// Indexes of a range that fall inside a sequence of `length` elements
+fn slice_indices(
+    start: i64,
+    end: i64,
+    step: i64,
+    inclusive: bool,
+    length: usize,
+) -> impl Iterator<Item = usize> {
+    let length = length as i64;
+
+    // Jump straight to the first value of the range that lands inside `0..length`
+    let outside = if step > 0 { -(start as i128) } else { start as i128 - (length as i128 - 1) };
+    let skipped = if outside > 0 { (outside + step.abs() as i128 - 1) / step.abs() as i128 } else { 0 };
+    let start = (start as i128 + skipped * step as i128) as i64;
+
+    range_values(start, end, step, inclusive)
+        .take_while(move |&i| (0..length).contains(&i))
+        .map(|i| i as usize)
+}
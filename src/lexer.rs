@@ -1,3 +1,7 @@
+use std::{collections::VecDeque, fmt};
+
+use unicode_xid::UnicodeXID;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum TT {
     Integer,
@@ -18,6 +22,7 @@ pub enum TT {
     KeyWord,
     Identifier,
     AssignmentOperator,
+    CompoundAssignmentOperator,
     EqualityOperator,
     InEqualityOperator,
     GreaterThan,
@@ -29,180 +34,720 @@ pub enum TT {
     Or,
     Xor,
     RightArrow,
+    FatArrow,
+    StringStart,
+    StringEnd,
+    InterpolationStart,
+    InterpolationEnd,
+    Range,
+    Ellipsis,
+    Error,
+}
+
+// Location of a token or node in the source file.
+// `line` and `column` are 1-based, `start` and `end` are byte offsets.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // Extends the span up to the end of `other`
+    pub fn to(self, other: Span) -> Self {
+        Span {
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
 }
 
+// An error found in the source code, reported without stopping
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Span) -> Self {
+        Diagnostic { message, span }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+const KEYWORDS: [&str; 15] = [
+    "let", "if", "else", "case", "of", "default", "as", "to", "func", "while", "break", "continue",
+    "for", "in", "return",
+];
+
 #[derive(Clone, Debug)]
 pub struct Token {
     pub kind: TT,
     pub value: String,
+    pub span: Span,
+    // `///` doc comments written right before the token
+    pub doc: Option<String>,
 }
 
 impl Token {
-    fn new(kind: TT, value: String) -> Self {
-        Token { kind, value }
+    fn new(kind: TT, value: String, span: Span) -> Self {
+        Token {
+            kind,
+            value,
+            span,
+            doc: None,
+        }
     }
 }
 
+// Lexes the source code through a byte cursor, producing tokens lazily as an iterator.
+// The source is never copied, so lexing stays linear in the size of the file.
 #[derive(Clone, Debug)]
 pub struct Lexer {
     source_code: String,
+    line: usize,
+    column: usize,
+    position: usize,
+    doc_lines: Vec<String>,
+    // Tokens lexed but not yet handed out, an interpolated string produces several at once
+    pending: VecDeque<Token>,
+    finished: bool,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Lexer {
     pub fn new(source_code: String) -> Self {
-        Lexer { source_code }
+        Lexer {
+            source_code,
+            line: 1,
+            column: 1,
+            position: 0,
+            doc_lines: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    // The part of the source code that hasn't been lexed yet
+    fn rest(&self) -> &str {
+        &self.source_code[self.position..]
+    }
+
+    fn current(&self) -> Option<char> {
+        self.rest().chars().next()
     }
 
     fn eat(&mut self) -> char {
-        let mut characters = self.source_code.chars();
-        let character = characters.next().unwrap();
-        self.source_code = characters.collect::<String>();
+        let character = self.current().unwrap();
+
+        self.position += character.len_utf8();
+        if character == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         character
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
-        let mut tokens = Vec::new();
-        const KEYWORDS: [&str; 9] = [
-            "let", "if", "else", "case", "of", "default", "as", "to", "func",
-        ];
+    // Zero width span at the current position
+    fn mark(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            start: self.position,
+            end: self.position,
+        }
+    }
 
-        while !self.source_code.is_empty() {
-            match self.source_code.chars().next().unwrap() {
-                ' ' | '\n' | '\t' => {
-                    self.eat();
+    // Makes a token spanning from `start` up to the current position, attaching any doc comments
+    // read since the previous token
+    fn token(&mut self, kind: TT, value: String, start: Span) -> Token {
+        let mut token = Token::new(kind, value, Span { end: self.position, ..start });
+        if !self.doc_lines.is_empty() {
+            token.doc = Some(self.doc_lines.join("\n"));
+            self.doc_lines.clear();
+        }
+        token
+    }
+
+    // Eats a single character and turns it into a token
+    fn eat_token(&mut self, kind: TT) -> Token {
+        let start = self.mark();
+        let value = self.eat().to_string();
+        self.token(kind, value, start)
+    }
+
+    // Turns everything from `start` up to the current position into an error token
+    fn error_token(&mut self, start: Span) -> Token {
+        let value = self.source_code[start.start..self.position].to_string();
+        self.token(TT::Error, value, start)
+    }
+
+    // Same as `error_token`, but also records a diagnostic for it
+    fn error(&mut self, message: String, start: Span) -> Token {
+        let token = self.error_token(start);
+        self.diagnostics.push(Diagnostic::new(message, token.span));
+        token
+    }
+
+    // Reads an escape sequence starting at the backslash and returns the character it stands for.
+    // Supported: \n \t \r \0 \\ \' \" \{ \} and \u{XXXX} with 1 to 6 hex digits.
+    fn lex_escape_sequence(&mut self) -> Result<char, Diagnostic> {
+        let start = self.mark();
+        self.eat();
+
+        let character = match self.current() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"' | '{' | '}')) => c,
+            Some('u') => return self.lex_unicode_escape(start),
+            Some(c) => {
+                let message = format!("Invalid escape sequence '\\{}'", c);
+                return Err(Diagnostic::new(message, start));
+            }
+            None => return Err(Diagnostic::new("Unterminated escape sequence".to_string(), start)),
+        };
+        self.eat();
+        Ok(character)
+    }
+
+    fn lex_unicode_escape(&mut self, start: Span) -> Result<char, Diagnostic> {
+        self.eat();
+        if !self.rest().starts_with('{') {
+            return Err(Diagnostic::new("Expected a '{' after '\\u'".to_string(), start));
+        }
+        self.eat();
+
+        let mut digits = String::new();
+        while let Some(c) = self.current() {
+            match c {
+                '}' => break,
+                c if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(self.eat()),
+                _ => {
+                    let message = format!("Invalid unicode escape '\\u{{{}{}'", digits, c);
+                    return Err(Diagnostic::new(message, start));
                 }
-                '+' | '-' | '*' | '/' | '%' => {
-                    if self.source_code.chars().nth(0).unwrap() == '/' {
-                        if self.source_code.starts_with("//") {
-                            self.eat();
-                            self.eat();
+            }
+        }
+        if self.rest().is_empty() {
+            return Err(Diagnostic::new("Unterminated unicode escape".to_string(), start));
+        }
+        self.eat();
 
-                            while !self.source_code.is_empty() {
-                                if self.source_code.chars().nth(0).unwrap() == '\n' {
-                                    break;
-                                };
-                                self.eat();
-                            }
-                        } else {
-                            tokens.push(Token::new(TT::BinaryOperator, self.eat().to_string()))
-                        };
-                    
-                    } else if self.source_code.chars().nth(0).unwrap() == '-' {
-                        if self.source_code.starts_with("->") {
-                            self.eat();
-                            self.eat();
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| {
+                let message = format!("Invalid unicode escape '\\u{{{}}}'", digits);
+                Diagnostic::new(message, start)
+            })
+    }
 
-                            tokens.push(Token::new(TT::RightArrow, "->".to_string()))
-                        } else {
-                            tokens.push(Token::new(TT::BinaryOperator, self.eat().to_string()))
-                        };
-                    
-                    } else {
-                        tokens.push(Token::new(TT::BinaryOperator, self.eat().to_string()))
+    // Lexes a quoted string. A string containing `{expression}` parts is emitted as a StringStart,
+    // its literal pieces as String tokens, each expression between InterpolationStart and
+    // InterpolationEnd, and finally a StringEnd. A string with errors in it becomes a single
+    // error token.
+    //
+    // Strings prefixed with `r` are raw: escapes and interpolation are ignored. Strings opened
+    // with three quotes are multi-line: a newline right after the opening quotes, the line of
+    // the closing quotes and the common indentation of the lines are stripped.
+    fn lex_string(&mut self, tokens: &mut Vec<Token>, raw: bool) {
+        let start = self.mark();
+        if raw {
+            self.eat();
+        }
+
+        let quote = self.current().unwrap();
+        let triple = quote.to_string().repeat(3);
+        let delimiter = if self.rest().starts_with(&triple) { triple } else { quote.to_string() };
+        let multiline = delimiter.len() > 1;
+        for _ in 0..delimiter.len() {
+            self.eat();
+        }
+        let opening_end = self.position;
+
+        let mut parts = Vec::new();
+        let mut word = "".to_string();
+        let mut malformed = false;
+        let (indentation, closing_on_own_line) = if multiline {
+            self.string_indentation(&delimiter, raw)
+        } else {
+            (0, false)
+        };
+
+        if multiline && (self.rest().starts_with('\n') || self.rest().starts_with("\r\n")) {
+            while self.eat() != '\n' {}
+            self.skip_indentation(indentation);
+        }
+        let mut word_start = self.mark();
+
+        loop {
+            match self.current() {
+                Some(_) if self.rest().starts_with(&delimiter) => break,
+                Some('\\') if !raw && self.rest().len() > 1 => match self.lex_escape_sequence() {
+                    Ok(c) => word.push(c),
+                    Err(diagnostic) => {
+                        self.diagnostics.push(diagnostic);
+                        malformed = true;
+                    }
+                },
+                Some('{') if !raw => {
+                    if !word.is_empty() {
+                        parts.push(self.token(TT::String, std::mem::take(&mut word), word_start));
                     }
+                    malformed |= !self.lex_interpolation(&mut parts);
+                    word_start = self.mark();
                 }
-                ';' => tokens.push(Token::new(TT::SemiColon, self.eat().to_string())),
-                ':' => tokens.push(Token::new(TT::Colon, self.eat().to_string())),
-                ',' => tokens.push(Token::new(TT::Comma, self.eat().to_string())),
-                '.' => tokens.push(Token::new(TT::Dot, self.eat().to_string())),
-                '(' => tokens.push(Token::new(TT::OpeningParenthesis, self.eat().to_string())),
-                ')' => tokens.push(Token::new(TT::ClosingParenthesis, self.eat().to_string())),
-                '{' => tokens.push(Token::new(TT::OpeningCurlyBrace, self.eat().to_string())),
-                '}' => tokens.push(Token::new(TT::ClosingCurlyBrace, self.eat().to_string())),
-                '[' => tokens.push(Token::new(TT::OpeningSquareBracket, self.eat().to_string())),
-                ']' => tokens.push(Token::new(TT::ClosingSquareBracket, self.eat().to_string())),
-                '&' => tokens.push(Token::new(TT::And, self.eat().to_string())),
-                '^' => tokens.push(Token::new(TT::Xor, self.eat().to_string())),
-                '|' => tokens.push(Token::new(TT::Or, self.eat().to_string())),
-                '>' => {
-                    let (kind, value) = if self.source_code.starts_with(">=") {
-                        self.eat();
-                        (TT::GreaterThanOrEqualTo, ">=")
-                    } else {
-                        (TT::GreaterThan, ">")
-                    };
+                Some('\r') if multiline && self.rest().starts_with("\r\n") => {
                     self.eat();
-                    tokens.push(Token::new(kind, value.to_string()));
                 }
-                '<' => {
-                    let (kind, value) = if self.source_code.starts_with("<=") {
-                        self.eat();
-                        (TT::LessThanOrEqualTo, "<=")
-                    } else {
-                        (TT::LessThan, "<")
-                    };
+                Some('\n') if multiline => {
+                    word.push(self.eat());
+                    self.skip_indentation(indentation);
+                }
+                Some(_) => word.push(self.eat()),
+                None => {
+                    tokens.push(self.error("Unterminated string".to_string(), start));
+                    return;
+                }
+            };
+        }
+
+        if closing_on_own_line {
+            if let Some(newline) = word.rfind('\n') {
+                word.truncate(newline);
+            }
+        }
+
+        let closing_start = self.mark();
+        for _ in 0..delimiter.len() {
+            self.eat();
+        }
+
+        if malformed {
+            tokens.push(self.error_token(start));
+        } else if parts.is_empty() {
+            tokens.push(self.token(TT::String, word, start));
+        } else {
+            if !word.is_empty() {
+                parts.push(self.token(TT::String, word, word_start));
+            }
+            tokens.push(Token::new(
+                TT::StringStart,
+                delimiter.clone(),
+                Span { end: opening_end, ..start },
+            ));
+            tokens.append(&mut parts);
+            tokens.push(self.token(TT::StringEnd, delimiter, closing_start));
+        }
+    }
+
+    // Looks ahead through a multi-line string, returning the smallest indentation of its
+    // non-blank lines and whether the closing quotes are on a line of their own
+    fn string_indentation(&self, delimiter: &str, raw: bool) -> (usize, bool) {
+        let rest = self.rest();
+        let mut end = rest.len();
+        let mut characters = rest.char_indices();
+
+        while let Some((i, c)) = characters.next() {
+            if rest[i..].starts_with(delimiter) {
+                end = i;
+                break;
+            } else if c == '\\' && !raw {
+                characters.next();
+            }
+        }
+
+        let lines: Vec<&str> = rest[..end].split('\n').collect();
+        let indentation = lines
+            .iter()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+            .min()
+            .unwrap_or(0);
+        let closing_on_own_line = lines.len() > 1 && lines[lines.len() - 1].trim().is_empty();
+
+        (indentation, closing_on_own_line)
+    }
+
+    // Skips up to `indentation` whitespace characters at the start of a line
+    fn skip_indentation(&mut self, indentation: usize) {
+        for _ in 0..indentation {
+            match self.current() {
+                Some(c) if c.is_whitespace() && c != '\n' => {
                     self.eat();
-                    tokens.push(Token::new(kind, value.to_string()));
                 }
-                '=' => {
-                    let (kind, value) = if self.source_code.starts_with("==") {
-                        self.eat();
-                        (TT::EqualityOperator, "==")
-                    } else {
-                        (TT::AssignmentOperator, "=")
-                    };
+                _ => break,
+            }
+        }
+    }
+
+    // Lexes the `{expression}` part of a string up to its matching closing brace.
+    // Returns false if the string ends before the closing brace.
+    fn lex_interpolation(&mut self, tokens: &mut Vec<Token>) -> bool {
+        let start = self.mark();
+        let mut depth = 0;
+        tokens.push(self.eat_token(TT::InterpolationStart));
+
+        loop {
+            match self.current() {
+                Some('}') if depth == 0 => break,
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some(_) => {}
+                None => {
+                    let diagnostic = Diagnostic::new("Unterminated interpolation".to_string(), start);
+                    self.diagnostics.push(diagnostic);
+                    return false;
+                }
+            };
+            self.lex_token(tokens);
+        }
+        tokens.push(self.eat_token(TT::InterpolationEnd));
+        true
+    }
+
+    // Lexes a number, turning a malformed one into an error token
+    fn lex_number(&mut self, tokens: &mut Vec<Token>) {
+        let start = self.mark();
+
+        match self.lex_number_literal() {
+            Ok(token) => tokens.push(token),
+            Err(diagnostic) => {
+                // Skip the rest of the malformed number so lexing resumes after it
+                while self.current().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.') {
                     self.eat();
-                    tokens.push(Token::new(kind, value.to_string()));
                 }
-                '!' => {
-                    let (kind, value) = if self.source_code.starts_with("!=") {
-                        self.eat();
-                        (TT::InEqualityOperator, "!=")
-                    } else {
-                        (TT::Not, "!")
-                    };
+                self.diagnostics.push(diagnostic);
+                tokens.push(self.error_token(start));
+            }
+        }
+    }
+
+    // Lexes an integer or real literal. Integers can be written in decimal or with a 0x, 0o or 0b
+    // prefix, reals can have a fraction and an exponent, and `_` may be used to separate digits.
+    // The token value is the number in plain decimal form.
+    fn lex_number_literal(&mut self) -> Result<Token, Diagnostic> {
+        let start = self.mark();
+
+        let radix = match self.rest().get(..2) {
+            Some("0x" | "0X") => 16,
+            Some("0o" | "0O") => 8,
+            Some("0b" | "0B") => 2,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            self.eat();
+            self.eat();
+
+            let digits = self.lex_digits(radix);
+            if digits.is_empty() {
+                let message = "Expected digits after the number prefix".to_string();
+                return Err(Diagnostic::new(message, start));
+            }
+            self.check_number_end(start)?;
+
+            let value = i64::from_str_radix(&digits, radix).map_err(|_| {
+                Diagnostic::new("Integer literal out of range".to_string(), start)
+            })?;
+            return Ok(self.token(TT::Integer, value.to_string(), start));
+        }
+
+        let mut number = self.lex_digits(10);
+        let mut is_real = false;
+
+        if self.rest().starts_with('.')
+            && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            number.push(self.eat());
+            number.push_str(&self.lex_digits(10));
+            is_real = true;
+        }
+
+        if self.rest().starts_with(['e', 'E']) {
+            number.push(self.eat());
+            if self.rest().starts_with(['+', '-']) {
+                number.push(self.eat());
+            }
+
+            let exponent = self.lex_digits(10);
+            if exponent.is_empty() {
+                let message = "Expected digits in the exponent of the number".to_string();
+                return Err(Diagnostic::new(message, start));
+            }
+            number.push_str(&exponent);
+            is_real = true;
+        }
+        self.check_number_end(start)?;
+
+        if is_real {
+            Ok(self.token(TT::Real, number, start))
+        } else if number.parse::<i64>().is_ok() {
+            Ok(self.token(TT::Integer, number, start))
+        } else {
+            Err(Diagnostic::new("Integer literal out of range".to_string(), start))
+        }
+    }
+
+    // Reads digits of the given radix, skipping `_` separators
+    fn lex_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+
+        while let Some(c) = self.current() {
+            match c {
+                '_' => {
                     self.eat();
-                    tokens.push(Token::new(kind, value.to_string()));
                 }
-                '\'' | '"' => {
-                    let start = self.eat();
-                    let mut word = "".to_string();
-
-                    while !self.source_code.is_empty() {
-                        match self.source_code.chars().next().unwrap() {
-                            c if c == start => {
-                                self.eat();
-                                break;
-                            }
-                            _ => word.push(self.eat()),
-                        };
-                    }
-                    tokens.push(Token::new(TT::String, word));
+                c if c.is_digit(radix) => digits.push(self.eat()),
+                _ => break,
+            };
+        }
+        digits
+    }
+
+    // A number can't run straight into letters, digits of another radix or a second fraction
+    fn check_number_end(&self, start: Span) -> Result<(), Diagnostic> {
+        let mut characters = self.rest().chars();
+
+        let unexpected = match (characters.next(), characters.next()) {
+            (Some(c), _) if c.is_alphanumeric() => c,
+            (Some('.'), Some(c)) if c.is_ascii_digit() => '.',
+            _ => return Ok(()),
+        };
+        let message = format!("Malformed number literal, unexpected '{}'", unexpected);
+        Err(Diagnostic::new(message, start))
+    }
+
+    // Reads a `///` line and keeps its text to attach to the next token
+    fn lex_doc_comment(&mut self) {
+        self.eat();
+        self.eat();
+        self.eat();
+
+        let mut line = String::new();
+        while !self.rest().is_empty() && !self.rest().starts_with('\n') {
+            line.push(self.eat());
+        }
+        let line = line.strip_prefix(' ').unwrap_or(&line).trim_end().to_string();
+        self.doc_lines.push(line);
+    }
+
+    // Skips a `/* ... */` comment, which may contain nested block comments
+    fn lex_block_comment(&mut self) {
+        let start = self.mark();
+        let mut depth = 0;
+
+        loop {
+            if self.rest().starts_with("/*") {
+                depth += 1;
+                self.eat();
+                self.eat();
+            } else if self.rest().starts_with("*/") {
+                depth -= 1;
+                self.eat();
+                self.eat();
+
+                if depth == 0 {
+                    break;
                 }
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    let mut word = self.eat().to_string();
-
-                    while !self.source_code.is_empty() {
-                        match self.source_code.chars().next().unwrap() {
-                            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => word.push(self.eat()),
-                            _ => break,
-                        };
-                    }
+            } else if self.rest().is_empty() {
+                let diagnostic = Diagnostic::new("Unterminated block comment".to_string(), start);
+                self.diagnostics.push(diagnostic);
+                break;
+            } else {
+                self.eat();
+            }
+        }
+    }
+
+    // Lexes the whole source code, returning the tokens and every error found along the way
+    pub fn tokenize(&mut self) -> (Vec<Token>, Vec<Diagnostic>) {
+        let tokens = self.by_ref().collect();
+        (tokens, std::mem::take(&mut self.diagnostics))
+    }
+
+    // Lexes whatever starts at the current position, pushing a token unless it was whitespace
+    // or a comment
+    fn lex_token(&mut self, tokens: &mut Vec<Token>) {
+        let start = self.mark();
+
+        match self.current().unwrap() {
+            c if c.is_whitespace() => {
+                self.eat();
+            }
+            '+' | '-' | '*' | '/' | '%' => {
+                if self.rest()[1..].starts_with('=') {
+                    let value = self.rest()[..2].to_string();
+                    self.eat();
+                    self.eat();
 
-                    if KEYWORDS.contains(&word.as_str()) {
-                        tokens.push(Token::new(TT::KeyWord, word));
+                    tokens.push(self.token(TT::CompoundAssignmentOperator, value, start))
+                } else if self.current().unwrap() == '/' {
+                    if self.rest().starts_with("///") && !self.rest().starts_with("////") {
+                        self.lex_doc_comment();
+                    } else if self.rest().starts_with("/*") {
+                        self.lex_block_comment();
+                    } else if self.rest().starts_with("//") {
+                        self.eat();
+                        self.eat();
+
+                        while !self.rest().is_empty() {
+                            if self.current().unwrap() == '\n' {
+                                break;
+                            };
+                            self.eat();
+                        }
                     } else {
-                        tokens.push(Token::new(TT::Identifier, word));
+                        tokens.push(self.eat_token(TT::BinaryOperator))
                     };
+                
+                } else if self.current().unwrap() == '-' {
+                    if self.rest().starts_with("->") {
+                        self.eat();
+                        self.eat();
+
+                        tokens.push(self.token(TT::RightArrow, "->".to_string(), start))
+                    } else {
+                        tokens.push(self.eat_token(TT::BinaryOperator))
+                    };
+                
+                } else {
+                    tokens.push(self.eat_token(TT::BinaryOperator))
                 }
-                '0'..='9' => {
-                    let mut number = self.eat().to_string();
-                    while !self.source_code.is_empty() {
-                        match self.source_code.chars().next().unwrap() {
-                            '0'..='9' | '.' => number.push(self.eat()),
-                            _ => break,
-                        };
+            }
+            ';' => tokens.push(self.eat_token(TT::SemiColon)),
+            ':' => tokens.push(self.eat_token(TT::Colon)),
+            ',' => tokens.push(self.eat_token(TT::Comma)),
+            '.' => {
+                if self.rest().starts_with("...") {
+                    for _ in 0..3 {
+                        self.eat();
                     }
-                    if number.contains('.') {
-                        tokens.push(Token::new(TT::Real, number));
-                    } else {
-                        tokens.push(Token::new(TT::Integer, number));
+                    tokens.push(self.token(TT::Ellipsis, "...".to_string(), start));
+                } else if self.rest().starts_with("..") {
+                    let value = if self.rest().starts_with("..=") { "..=" } else { ".." };
+                    for _ in 0..value.len() {
+                        self.eat();
+                    }
+                    tokens.push(self.token(TT::Range, value.to_string(), start));
+                } else {
+                    tokens.push(self.eat_token(TT::Dot));
+                }
+            }
+            '(' => tokens.push(self.eat_token(TT::OpeningParenthesis)),
+            ')' => tokens.push(self.eat_token(TT::ClosingParenthesis)),
+            '{' => tokens.push(self.eat_token(TT::OpeningCurlyBrace)),
+            '}' => tokens.push(self.eat_token(TT::ClosingCurlyBrace)),
+            '[' => tokens.push(self.eat_token(TT::OpeningSquareBracket)),
+            ']' => tokens.push(self.eat_token(TT::ClosingSquareBracket)),
+            '&' => tokens.push(self.eat_token(TT::And)),
+            '^' => tokens.push(self.eat_token(TT::Xor)),
+            '|' => tokens.push(self.eat_token(TT::Or)),
+            '>' => {
+                let (kind, value) = if self.rest().starts_with(">=") {
+                    self.eat();
+                    (TT::GreaterThanOrEqualTo, ">=")
+                } else {
+                    (TT::GreaterThan, ">")
+                };
+                self.eat();
+                tokens.push(self.token(kind, value.to_string(), start));
+            }
+            '<' => {
+                let (kind, value) = if self.rest().starts_with("<=") {
+                    self.eat();
+                    (TT::LessThanOrEqualTo, "<=")
+                } else {
+                    (TT::LessThan, "<")
+                };
+                self.eat();
+                tokens.push(self.token(kind, value.to_string(), start));
+            }
+            '=' => {
+                let (kind, value) = if self.rest().starts_with("==") {
+                    self.eat();
+                    (TT::EqualityOperator, "==")
+                } else if self.rest().starts_with("=>") {
+                    self.eat();
+                    (TT::FatArrow, "=>")
+                } else {
+                    (TT::AssignmentOperator, "=")
+                };
+                self.eat();
+                tokens.push(self.token(kind, value.to_string(), start));
+            }
+            '!' => {
+                let (kind, value) = if self.rest().starts_with("!=") {
+                    self.eat();
+                    (TT::InEqualityOperator, "!=")
+                } else {
+                    (TT::Not, "!")
+                };
+                self.eat();
+                tokens.push(self.token(kind, value.to_string(), start));
+            }
+            '\'' | '"' => self.lex_string(tokens, false),
+            'r' if self.rest()[1..].starts_with(['\'', '"']) => self.lex_string(tokens, true),
+            c if c == '_' || c.is_xid_start() => {
+                let mut word = self.eat().to_string();
+
+                while !self.rest().is_empty() {
+                    match self.current().unwrap() {
+                        c if c.is_xid_continue() => word.push(self.eat()),
+                        _ => break,
                     };
                 }
-                _ => panic!("Invalid token '{}' found", self.eat()),
-            };
+
+                if KEYWORDS.contains(&word.as_str()) {
+                    tokens.push(self.token(TT::KeyWord, word, start));
+                } else {
+                    tokens.push(self.token(TT::Identifier, word, start));
+                };
+            }
+            '0'..='9' => self.lex_number(tokens),
+            _ => {
+                let character = self.eat();
+                tokens.push(self.error(format!("Invalid token '{}' found", character), start));
+            }
+        };
+    }
+}
+
+impl Iterator for Lexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while self.pending.is_empty() && !self.finished {
+            if self.rest().is_empty() {
+                let eof = self.token(TT::Eof, "".to_string(), self.mark());
+                self.pending.push_back(eof);
+                self.finished = true;
+            } else {
+                let mut tokens = Vec::new();
+                self.lex_token(&mut tokens);
+                self.pending.extend(tokens);
+            }
         }
-        tokens.push(Token::new(TT::Eof, "".to_string()));
-        tokens
+        self.pending.pop_front()
     }
 }
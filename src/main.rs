@@ -1,6 +1,4 @@
-use std::{
-    env, fs::File, io::{BufRead, BufReader}, path::PathBuf
-};
+use std::{env, fs, path::PathBuf, process};
 
 use interpreter::Interpreter;
 use lexer::Lexer;
@@ -16,27 +14,33 @@ mod methods;
 mod parser;
 mod values;
 
-fn read_file(path: PathBuf) -> Vec<String> {
-    let file = File::open(path).expect("Failed to open file");
-    let content: Vec<String> = BufReader::new(&file)
-        .lines()
-        .map(|line| line.unwrap())
-        .collect();
-    content
+// Reads the file as is, so line endings stay intact and token spans match the file's byte offsets
+fn read_file(path: PathBuf) -> String {
+    fs::read_to_string(path).expect("Failed to open file")
 }
 
 fn main() {
     let mut arguments: Vec<String> = env::args().collect();
     arguments.remove(0);
 
-    if let Some(source_file) = arguments.get(0) {
-        let source_code = read_file(PathBuf::from(source_file)).join("\n");
+    if let Some(source_file) = arguments.first() {
+        let source_code = read_file(PathBuf::from(source_file));
 
         let mut lexer = Lexer::new(source_code);
-        let tokens = lexer.tokenize();
+        let (tokens, mut diagnostics) = lexer.tokenize();
 
+        // The parser still runs after lexer errors, so every mistake in the file is reported at once
         let mut parser = Parser::new(tokens);
-        let program = parser.generate_ast();
+        let (program, errors) = parser.generate_ast();
+        diagnostics.extend(errors);
+
+        if !diagnostics.is_empty() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            for diagnostic in diagnostics {
+                eprintln!("{}: {}", source_file, diagnostic);
+            }
+            process::exit(1);
+        }
 
         let interpreter = Interpreter::new(program);
         let _result = interpreter.interpret(&mut Environment::new(None));
@@ -1,52 +1,52 @@
-use crate::values::RuntimeValue;
+use crate::values::{range_count, RuntimeValue};
 
 // Array Methods
 
-pub fn array_length(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn array_length(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .length() method for array
 
     match &object {
-        RuntimeValue::Array(a, _) => RuntimeValue::Integer(a.len() as i64),
-        _ => RuntimeValue::Null,
+        RuntimeValue::Array(a, _) => Ok(RuntimeValue::Integer(a.len() as i64)),
+        _ => Ok(RuntimeValue::Null),
     }
 }
 
-pub fn array_is_empty(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn array_is_empty(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .is_empty() method for array
 
     match &object {
-        RuntimeValue::Array(a, _) => RuntimeValue::Boolean(a.is_empty()),
-        _ => RuntimeValue::Null,
+        RuntimeValue::Array(a, _) => Ok(RuntimeValue::Boolean(a.is_empty())),
+        _ => Ok(RuntimeValue::Null),
     }
 }
 
 // String Methods
 
-pub fn string_length(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn string_length(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .length() method for string
 
     match &object {
-        RuntimeValue::String(s, _) => RuntimeValue::Integer(s.len() as i64),
-        _ => RuntimeValue::Null,
+        RuntimeValue::String(s, _) => Ok(RuntimeValue::Integer(s.len() as i64)),
+        _ => Ok(RuntimeValue::Null),
     }
 }
 
-pub fn string_is_empty(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn string_is_empty(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .is_empty() method for string
 
     match &object {
-        RuntimeValue::String(s, _) => RuntimeValue::Boolean(s.is_empty()),
-        _ => panic!()
+        RuntimeValue::String(s, _) => Ok(RuntimeValue::Boolean(s.is_empty())),
+        _ => Err(format!("Cannot call 'is_empty' on '{}'", object)),
     }
 }
 
-pub fn string_split(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn string_split(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     let mut splitted_string = Vec::new();
 
     if arguments.len() == 1 {
         let split_char = match &arguments[0] {
-            RuntimeValue::String(s, _) => s,
-            _ => panic!()
+            RuntimeValue::String(s, _) if !s.is_empty() => s,
+            other => return Err(format!("Expected a non-empty string separator, found '{}'", other)),
         };
         let mut split = String::new();
 
@@ -63,31 +63,69 @@ pub fn string_split(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> Runti
                     }
                 }
             },
-            _ => panic!()
+            other => return Err(format!("Cannot call 'split' on '{}'", other)),
         }
     } else {
-        panic!("Expected 1 argument, recieved {}", arguments.len())
+        return Err(format!("Expected 1 argument, recieved {}", arguments.len()));
     }
 
-    RuntimeValue::array(splitted_string)
+    Ok(RuntimeValue::array(splitted_string))
 }
 
 // Object Methods
 
-pub fn object_length(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn object_length(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .length() method for objects
 
     match &object {
-        RuntimeValue::Object(o, _) => RuntimeValue::Integer(o.len() as i64),
-        _ => RuntimeValue::Null,
+        RuntimeValue::Object(o, _) => Ok(RuntimeValue::Integer(o.len() as i64)),
+        _ => Ok(RuntimeValue::Null),
     }
 }
 
-pub fn object_is_empty(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> RuntimeValue {
+pub fn object_is_empty(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
     // .is_empty() method for object
 
     match &object {
-        RuntimeValue::Object(o, _) => RuntimeValue::Boolean(o.is_empty()),
-        _ => RuntimeValue::Null,
+        RuntimeValue::Object(o, _) => Ok(RuntimeValue::Boolean(o.is_empty())),
+        _ => Ok(RuntimeValue::Null),
+    }
+}
+
+// Range Methods
+
+pub fn range_step(object: RuntimeValue, arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
+    // .step() method for range, returns the same range counting in steps of the argument
+
+    match (&object, arguments.first()) {
+        (RuntimeValue::Range(_, _, _, _, _), Some(RuntimeValue::Integer(0))) => {
+            Err("Range step cannot be 0".to_string())
+        }
+        (RuntimeValue::Range(start, end, _, inclusive, _), Some(RuntimeValue::Integer(step))) => {
+            Ok(RuntimeValue::range(*start, *end, *step, *inclusive))
+        }
+        _ => Err("Expected an integer step".to_string()),
+    }
+}
+
+pub fn range_length(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
+    // .length() method for range
+
+    match &object {
+        RuntimeValue::Range(start, end, step, inclusive, _) => {
+            Ok(RuntimeValue::Integer(range_count(*start, *end, *step, *inclusive)))
+        }
+        _ => Ok(RuntimeValue::Null),
+    }
+}
+
+pub fn range_is_empty(object: RuntimeValue, _arguments: Vec<RuntimeValue>) -> Result<RuntimeValue, String> {
+    // .is_empty() method for range
+
+    match &object {
+        RuntimeValue::Range(start, end, step, inclusive, _) => {
+            Ok(RuntimeValue::Boolean(range_count(*start, *end, *step, *inclusive) == 0))
+        }
+        _ => Ok(RuntimeValue::Null),
     }
 }
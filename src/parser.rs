@@ -1,411 +1,874 @@
-use core::panic;
+use std::collections::VecDeque;
 
-use crate::lexer::{Token, TT};
+use crate::lexer::{Diagnostic, Span, Token, TT};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ASTNode {
-    StringLiteral(String),
-    IntegerLiteral(i64),
-    RealLiteral(f64),
-    ArrayLiteral(Vec<ASTNode>),
-    NullLiteral,
-    Identifier(String),
-
-    ConditionalExpression(Box<ASTNode>, String, Box<ASTNode>),
-    CallExpression(Box<ASTNode>, Vec<ASTNode>),
-    BinaryExpression(Box<ASTNode>, String, Box<ASTNode>),
-    AssignmentExpression(Box<ASTNode>, Box<ASTNode>),
-    UnaryExpression(String, Box<ASTNode>),
-    MemberExpression(Box<ASTNode>, Box<ASTNode>, bool),
-    ConversionExpression(Box<ASTNode>, Box<ASTNode>),
-
-    VariableDeclaration(Box<ASTNode>, Box<ASTNode>),
-    FunctionDeclaration(Box<ASTNode>, Vec<ASTNode>, Vec<ASTNode>, Box<ASTNode>, Box<ASTNode>),
-    IfStatement(Box<ASTNode>, Box<ASTNode>, Box<ASTNode>),
-    Program(Vec<ASTNode>),
-    CaseStatement(Box<ASTNode>, Vec<ASTNode>),
-    Case(Box<ASTNode>, Box<ASTNode>),
+    // Every node carries the span of the source it was parsed from as its last field
+    StringLiteral(String, Span),
+    IntegerLiteral(i64, Span),
+    RealLiteral(f64, Span),
+    ArrayLiteral(Vec<ASTNode>, Span),
+    TupleLiteral(Vec<ASTNode>, Span),
+    ObjectLiteral(Vec<(String, ASTNode)>, Span),
+    InterpolatedString(Vec<ASTNode>, Span),
+    NullLiteral(Span),
+    Identifier(String, Span),
+
+    ConditionalExpression(Box<ASTNode>, String, Box<ASTNode>, Span),
+    LogicalExpression(Box<ASTNode>, String, Box<ASTNode>, Span),
+    CallExpression(Box<ASTNode>, Vec<ASTNode>, Span),
+    BinaryExpression(Box<ASTNode>, String, Box<ASTNode>, Span),
+    AssignmentExpression(Box<ASTNode>, Box<ASTNode>, Span),
+    // target, operator without the '=' and value
+    CompoundAssignmentExpression(Box<ASTNode>, String, Box<ASTNode>, Span),
+    UnaryExpression(String, Box<ASTNode>, Span),
+    MemberExpression(Box<ASTNode>, Box<ASTNode>, bool, Span),
+    ConversionExpression(Box<ASTNode>, Box<ASTNode>, Span),
+    // start, end and whether the end is included
+    RangeExpression(Box<ASTNode>, Box<ASTNode>, bool, Span),
+
+    // an expression followed by ';', its value is thrown away
+    ExpressionStatement(Box<ASTNode>, Span),
+    VariableDeclaration(Box<ASTNode>, Box<ASTNode>, Span),
+    // element patterns and the rest pattern
+    ArrayPattern(Vec<ASTNode>, Option<Box<ASTNode>>, Span),
+    // property names with their patterns and the rest pattern
+    ObjectPattern(Vec<(String, ASTNode)>, Option<Box<ASTNode>>, Span),
+    // name, parameters, parameter types, return type, body and doc comment
+    FunctionDeclaration(Box<ASTNode>, Vec<ASTNode>, Vec<ASTNode>, Box<ASTNode>, Box<ASTNode>, Option<String>, Span),
+    // parameters, parameter types, return type and body of a function without a name
+    FunctionExpression(Vec<ASTNode>, Vec<ASTNode>, Box<ASTNode>, Box<ASTNode>, Span),
+    IfStatement(Box<ASTNode>, Box<ASTNode>, Box<ASTNode>, Span),
+    WhileStatement(Box<ASTNode>, Box<ASTNode>, Span),
+    // loop variables, iterated value and body
+    ForStatement(Vec<ASTNode>, Box<ASTNode>, Box<ASTNode>, Span),
+    BreakStatement(Span),
+    ContinueStatement(Span),
+    ReturnStatement(Box<ASTNode>, Span),
+    Program(Vec<ASTNode>, Span),
+    CaseStatement(Box<ASTNode>, Vec<ASTNode>, Span),
+    Case(Box<ASTNode>, Box<ASTNode>, Span),
 }
 
+impl ASTNode {
+    pub fn span(&self) -> Span {
+        match self {
+            ASTNode::StringLiteral(_, span)
+            | ASTNode::IntegerLiteral(_, span)
+            | ASTNode::RealLiteral(_, span)
+            | ASTNode::ArrayLiteral(_, span)
+            | ASTNode::TupleLiteral(_, span)
+            | ASTNode::ObjectLiteral(_, span)
+            | ASTNode::InterpolatedString(_, span)
+            | ASTNode::NullLiteral(span)
+            | ASTNode::Identifier(_, span)
+            | ASTNode::ConditionalExpression(_, _, _, span)
+            | ASTNode::LogicalExpression(_, _, _, span)
+            | ASTNode::CallExpression(_, _, span)
+            | ASTNode::BinaryExpression(_, _, _, span)
+            | ASTNode::AssignmentExpression(_, _, span)
+            | ASTNode::CompoundAssignmentExpression(_, _, _, span)
+            | ASTNode::UnaryExpression(_, _, span)
+            | ASTNode::MemberExpression(_, _, _, span)
+            | ASTNode::ConversionExpression(_, _, span)
+            | ASTNode::RangeExpression(_, _, _, span)
+            | ASTNode::ExpressionStatement(_, span)
+            | ASTNode::VariableDeclaration(_, _, span)
+            | ASTNode::ArrayPattern(_, _, span)
+            | ASTNode::ObjectPattern(_, _, span)
+            | ASTNode::FunctionDeclaration(_, _, _, _, _, _, span)
+            | ASTNode::FunctionExpression(_, _, _, _, span)
+            | ASTNode::IfStatement(_, _, _, span)
+            | ASTNode::WhileStatement(_, _, span)
+            | ASTNode::ForStatement(_, _, _, span)
+            | ASTNode::BreakStatement(span)
+            | ASTNode::ContinueStatement(span)
+            | ASTNode::ReturnStatement(_, span)
+            | ASTNode::Program(_, span)
+            | ASTNode::CaseStatement(_, _, span)
+            | ASTNode::Case(_, _, span) => *span,
+        }
+    }
+}
+
+// Infix operators, from the loosest to the tightest binding:
+//
+// | Operators                          | Node                   | Associativity |
+// | ---------------------------------- | ---------------------- | ------------- |
+// | `=` `+=` `-=` `*=` `/=` `%=`       | AssignmentExpression   | none          |
+// | `|`                                | LogicalExpression      | left          |
+// | `^`                                | LogicalExpression      | left          |
+// | `&`                                | LogicalExpression      | left          |
+// | `==` `!=` `<` `>` `<=` `>=` `in`   | ConditionalExpression  | left          |
+// | `..` `..=`                         | RangeExpression        | none          |
+// | `+` `-`                            | BinaryExpression       | left          |
+// | `*` `/` `%`                        | BinaryExpression       | left          |
+// | `as` `to`                          | ConversionExpression   | left          |
+// | prefix `-` `+` `!`                 | UnaryExpression        | right         |
+// | `()` `[]` `.`                      | Call/MemberExpression  | left          |
+//
+// Assignments are handled by `parse_assignment_expression` and can't be chained, as they can
+// only be statements. Prefix and postfix operators are handled by `parse_prefix_expression` and
+// `parse_postfix_expression`.
+#[derive(Clone, Copy)]
+enum Operator {
+    Or,
+    Xor,
+    And,
+    Comparison,
+    Range,
+    Additive,
+    Multiplicative,
+    Conversion,
+}
+
+impl Operator {
+    // Minimum power of the operand of a prefix operator, so only postfix operators bind tighter
+    const PREFIX: u8 = 17;
+
+    fn infix(token: &Token) -> Option<Self> {
+        match (&token.kind, token.value.as_str()) {
+            (TT::Or, _) => Some(Operator::Or),
+            (TT::Xor, _) => Some(Operator::Xor),
+            (TT::And, _) => Some(Operator::And),
+            (
+                TT::EqualityOperator
+                | TT::InEqualityOperator
+                | TT::GreaterThan
+                | TT::LessThan
+                | TT::GreaterThanOrEqualTo
+                | TT::LessThanOrEqualTo,
+                _,
+            )
+            | (TT::KeyWord, "in") => Some(Operator::Comparison),
+            (TT::Range, _) => Some(Operator::Range),
+            (TT::BinaryOperator, "+" | "-") => Some(Operator::Additive),
+            (TT::BinaryOperator, "*" | "/" | "%") => Some(Operator::Multiplicative),
+            (TT::KeyWord, "as" | "to") => Some(Operator::Conversion),
+            _ => None,
+        }
+    }
+
+    // How tightly the operator binds, the operand on its right is parsed with a minimum one higher
+    fn power(self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::Xor => 3,
+            Operator::And => 5,
+            Operator::Comparison => 7,
+            Operator::Range => 9,
+            Operator::Additive => 11,
+            Operator::Multiplicative => 13,
+            Operator::Conversion => 15,
+        }
+    }
+}
+
+// Keywords that start a statement, where parsing picks up again after a syntax error
+const STATEMENT_KEYWORDS: [&str; 9] =
+    ["let", "if", "case", "func", "while", "for", "break", "continue", "return"];
+
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: VecDeque<Token>,
+    previous: Span,
+    // Number of loops around the code being parsed, `break` and `continue` need at least one
+    loop_depth: usize,
+    // Number of function bodies around the code being parsed, `return` needs at least one
+    function_depth: usize,
+    // Syntax errors of the statements that were skipped
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens }
+        Parser {
+            tokens: tokens.into(),
+            previous: Span::default(),
+            loop_depth: 0,
+            function_depth: 0,
+            errors: Vec::new(),
+        }
     }
 
+    // The Eof token is never removed, so there is always a token to look at
     fn eat(&mut self) -> Token {
-        self.tokens.remove(0)
+        let token = if self.tokens.len() > 1 {
+            self.tokens.pop_front().unwrap()
+        } else {
+            self.tokens[0].clone()
+        };
+        self.previous = token.span;
+        token
     }
 
-    fn expect(&mut self, expectation: TT) -> Token {
-        let token = &self.peek();
+    fn expect(&mut self, expectation: TT) -> Result<Token, Diagnostic> {
+        let token = self.peek();
         if token.kind == expectation {
-            self.eat()
+            Ok(self.eat())
         } else {
-            panic!(
-                "Expected a '{:?}' but found a '{:?}'",
-                expectation, token.kind
-            );
+            // A missing ';' is reported where the statement ends rather than at whatever follows it,
+            // which is usually on the next line
+            let span = if expectation == TT::SemiColon { self.previous } else { token.span };
+            Err(Diagnostic::new(
+                format!("Expected a '{:?}' but found a '{:?}'", expectation, token.kind),
+                span,
+            ))
         }
     }
 
-    fn peek(&mut self) -> &Token {
+    // Span from `start` up to the end of the last eaten token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous)
+    }
+
+    fn peek(&self) -> &Token {
         &self.tokens[0]
     }
 
-    pub fn generate_ast(&mut self) -> ASTNode {
-        let mut program_body = Vec::new();
+    // Parses the whole program. Statements with syntax errors are left out of the tree and their
+    // errors are returned alongside it.
+    pub fn generate_ast(&mut self) -> (ASTNode, Vec<Diagnostic>) {
+        let start = self.peek().span;
+        let program_body = self.parse_statements(TT::Eof);
+
+        let program = ASTNode::Program(program_body, self.span_from(start));
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    // Parses statements up to the `end` token. A statement that fails to parse is recorded and
+    // skipped, so the ones after it are still checked.
+    fn parse_statements(&mut self, end: TT) -> Vec<ASTNode> {
+        let mut body = Vec::new();
+
+        while self.peek().kind != end && self.peek().kind != TT::Eof {
+            // A ';' on its own is an empty statement
+            if self.peek().kind == TT::SemiColon {
+                self.eat();
+                continue;
+            }
+
+            let start = self.peek().span;
+            match self.parse() {
+                Ok(statement) => body.push(statement),
+                Err(error) => {
+                    // The lexer has already reported the tokens it couldn't read
+                    if self.peek().kind != TT::Error {
+                        self.errors.push(error);
+                    }
+                    self.synchronize(start);
 
-        while self.peek().kind != TT::Eof {
-            program_body.push(self.parse());
+                    // A '}' that doesn't close this block has no block to close
+                    if self.peek().kind == TT::ClosingCurlyBrace && end != TT::ClosingCurlyBrace {
+                        self.eat();
+                    }
+                }
+            }
+        }
+        body
+    }
+
+    // Skips to the end of the broken statement that began at `start`: past the next ';', up to the
+    // '}' closing the block it is in, or up to a keyword that starts a new statement. Braces opened
+    // while skipping are skipped as a whole.
+    fn synchronize(&mut self, start: Span) {
+        let mut depth = 0;
+
+        loop {
+            let token = self.peek();
+            match token.kind {
+                TT::Eof => break,
+                TT::SemiColon if depth == 0 => {
+                    self.eat();
+                    break;
+                }
+                TT::ClosingCurlyBrace if depth == 0 => break,
+                TT::KeyWord
+                    if depth == 0 && token.span != start && STATEMENT_KEYWORDS.contains(&token.value.as_str()) =>
+                {
+                    break
+                }
+                TT::OpeningCurlyBrace => depth += 1,
+                TT::ClosingCurlyBrace => depth -= 1,
+                _ => {}
+            }
+            self.eat();
         }
+    }
 
-        ASTNode::Program(program_body)
+    // Parses `{`, the statements of a block and `}`
+    fn parse_block(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.expect(TT::OpeningCurlyBrace)?.span;
+        let body = self.parse_statements(TT::ClosingCurlyBrace);
+        self.expect(TT::ClosingCurlyBrace)?;
+
+        Ok(ASTNode::Program(body, self.span_from(start)))
     }
 
-    fn parse(&mut self) -> ASTNode {
+    fn parse(&mut self) -> Result<ASTNode, Diagnostic> {
         match self.peek().kind {
+            // `func` followed by `(` starts a function expression rather than a declaration
+            TT::KeyWord if self.peek().value == "func" && self.tokens[1].kind == TT::OpeningParenthesis => {
+                self.parse_expression_statement()
+            }
             TT::KeyWord => self.parse_statement(),
-            _ => self.parse_expression(),
+            _ => self.parse_expression_statement(),
+        }
+    }
+
+    // An expression is a statement when followed by ';'. Without the ';' it must be the last thing
+    // in its block, and is the value of the block.
+    fn parse_expression_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let expression = self.parse_assignment_expression()?;
+
+        match self.peek().kind {
+            TT::SemiColon => {
+                self.eat();
+                let span = self.span_from(expression.span());
+                Ok(ASTNode::ExpressionStatement(Box::new(expression), span))
+            }
+            TT::ClosingCurlyBrace | TT::Eof => Ok(expression),
+            _ => Err(Diagnostic::new(
+                format!("Expected a ';' after the expression, got '{}'", self.peek().value),
+                self.previous,
+            )),
         }
     }
 
-    fn parse_statement(&mut self) -> ASTNode {
+    fn parse_statement(&mut self) -> Result<ASTNode, Diagnostic> {
         match self.peek().value.as_str() {
             "let" => self.parse_variable_declaration(),
-            "if" => self.parse_if_statement(),
-            "case" => self.parse_case_statement(),
+            "if" | "case" => {
+                let expression = if self.peek().value == "if" {
+                    self.parse_if_statement()?
+                } else {
+                    self.parse_case_statement()?
+                };
+
+                // Followed by ';' its value is thrown away, like any other expression
+                if self.peek().kind == TT::SemiColon {
+                    self.eat();
+                    let span = self.span_from(expression.span());
+                    return Ok(ASTNode::ExpressionStatement(Box::new(expression), span));
+                }
+                Ok(expression)
+            }
             "func" => self.parse_function_declaration(),
-            _ => panic!("Invalid keyword found '{}'", self.eat().value),
+            "while" => self.parse_while_statement(),
+            "for" => self.parse_for_statement(),
+            "break" | "continue" => self.parse_loop_control_statement(),
+            "return" => self.parse_return_statement(),
+            _ => {
+                let token = self.peek();
+                Err(Diagnostic::new(format!("Invalid keyword found '{}'", token.value), token.span))
+            }
         }
     }
 
-    fn parse_function_declaration(&mut self) -> ASTNode {
-        self.eat();
+    fn parse_function_declaration(&mut self) -> Result<ASTNode, Diagnostic> {
+        let keyword = self.eat();
+        let start = keyword.span;
 
-        let name = self.parse_primary_expression();
-        assert!(matches!(&name, ASTNode::Identifier(_)));
+        let name = self.expect(TT::Identifier)?;
+        let name = ASTNode::Identifier(name.value, name.span);
 
-        if self.peek().kind == TT::OpeningParenthesis {
-            let mut parameters = Vec::new();
-            let mut parameters_types = Vec::new();
-            self.eat();
+        let (parameters, parameters_types, return_type, body) = self.parse_function()?;
+        Ok(ASTNode::FunctionDeclaration(Box::new(name), parameters, parameters_types, Box::new(return_type), Box::new(body), keyword.doc, self.span_from(start)))
+    }
 
-            while self.peek().kind != TT::ClosingParenthesis {
-                parameters.push(self.parse());
+    fn parse_function_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
 
-                if self.peek().kind == TT::Colon {
-                    self.eat();
-                    let kind = self.parse_primary_expression();
+        let (parameters, parameters_types, return_type, body) = self.parse_function()?;
+        Ok(ASTNode::FunctionExpression(parameters, parameters_types, Box::new(return_type), Box::new(body), self.span_from(start)))
+    }
 
-                    match kind {
-                        ASTNode::Identifier(_) => {
-                            parameters_types.push(kind);
-                        },
-                        _ => panic!()
-                    }
+    // Parses everything after the name of a function: the parameters, the return type and either
+    // a block body or `=>` followed by a single expression
+    fn parse_function(&mut self) -> Result<(Vec<ASTNode>, Vec<ASTNode>, ASTNode, ASTNode), Diagnostic> {
+        let mut parameters = Vec::new();
+        let mut parameters_types = Vec::new();
+        self.expect(TT::OpeningParenthesis)?;
 
-                    if self.peek().kind == TT::ClosingParenthesis {
-                        break;
-                    }
-                    self.expect(TT::Comma);
-                } else {
-                    panic!()
-                }
-            };
-            self.eat();
+        while self.peek().kind != TT::ClosingParenthesis {
+            let parameter = self.expect(TT::Identifier)?;
+            parameters.push(ASTNode::Identifier(parameter.value, parameter.span));
 
-            self.expect(TT::RightArrow);
+            self.expect(TT::Colon)?;
+            let kind = self.expect(TT::Identifier)?;
+            parameters_types.push(ASTNode::Identifier(kind.value, kind.span));
 
-            let return_type = self.parse_primary_expression();
-            match return_type {
-                ASTNode::Identifier(_) => {
-                    if self.peek().kind == TT::OpeningCurlyBrace {
-                        self.eat();
-                        let mut body = Vec::new();
-        
-                        while self.peek().kind != TT::ClosingCurlyBrace {
-                            body.push(self.parse());
-                        };
-                        self.eat();
-        
-                        ASTNode::FunctionDeclaration(Box::new(name), parameters, parameters_types, Box::new(return_type), Box::new(ASTNode::Program(body)))
-                    } else {
-                        panic!("Expected a '{{', got '{:?}'", self.peek());
-                    }
-                },
-                _ => panic!("Expected a type, got '{:?}'", return_type)
+            if self.peek().kind == TT::ClosingParenthesis {
+                break;
             }
-        } else {
-            panic!("Expected '(', got '{:?}'", self.peek());
+            self.expect(TT::Comma)?;
         }
+        self.eat();
+
+        self.expect(TT::RightArrow)?;
+        let return_type = self.expect(TT::Identifier)?;
+        let return_type = ASTNode::Identifier(return_type.value, return_type.span);
+
+        // Loops around the function can't be broken out of from its body
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.function_depth += 1;
+
+        let body = if self.peek().kind == TT::FatArrow {
+            let body_start = self.eat().span;
+            self.parse_expression()
+                .map(|value| ASTNode::Program(vec![value], self.span_from(body_start)))
+        } else if self.peek().kind == TT::OpeningCurlyBrace {
+            self.parse_block()
+        } else {
+            Err(Diagnostic::new(
+                format!("Expected a '{{' or '=>', got '{:?}'", self.peek().kind),
+                self.peek().span,
+            ))
+        };
+        self.loop_depth = loop_depth;
+        self.function_depth -= 1;
+
+        Ok((parameters, parameters_types, return_type, body?))
     }
 
-    fn parse_case_statement(&mut self) -> ASTNode {
-        self.eat();
+    fn parse_while_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
 
-        let value = self.parse_expression();
+        let condition = self.parse_expression()?;
 
-        if self.peek().kind == TT::KeyWord && self.peek().value == "of" {
-            self.eat();
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+
+        Ok(ASTNode::WhileStatement(Box::new(condition), Box::new(body?), self.span_from(start)))
+    }
 
-            let mut cases = Vec::new();
+    fn parse_for_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
+        let mut variables = Vec::new();
 
-            self.expect(TT::OpeningCurlyBrace);
+        loop {
+            let variable = self.expect(TT::Identifier)?;
+            variables.push(ASTNode::Identifier(variable.value, variable.span));
 
-            while self.peek().kind != TT::ClosingCurlyBrace {
-                cases.push(self.parse_case());
+            if self.peek().kind == TT::Comma && variables.len() < 2 {
+                self.eat();
+            } else {
+                break;
             }
-            self.expect(TT::ClosingCurlyBrace);
-            self.expect(TT::SemiColon);
+        }
 
-            ASTNode::CaseStatement(Box::new(value), cases)
-        } else {
-            panic!("Expected an 'of', got '{:?}'", self.peek());
+        if !(self.peek().kind == TT::KeyWord && self.peek().value == "in") {
+            return Err(Diagnostic::new(format!("Expected an 'in', got '{}'", self.peek().value), self.peek().span));
         }
+        self.eat();
+
+        let iterable = self.parse_expression()?;
+
+        self.loop_depth += 1;
+        let body = self.parse_block();
+        self.loop_depth -= 1;
+
+        Ok(ASTNode::ForStatement(variables, Box::new(iterable), Box::new(body?), self.span_from(start)))
     }
 
-    fn parse_case(&mut self) -> ASTNode {
-        let case = self.parse_expression();
-        let mut body = Vec::new();
+    fn parse_loop_control_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let keyword = self.eat();
+
+        if self.loop_depth == 0 {
+            return Err(Diagnostic::new(format!("'{}' outside of a loop", keyword.value), keyword.span));
+        }
+        self.expect(TT::SemiColon)?;
 
-        self.expect(TT::Colon);
+        if keyword.value == "break" {
+            Ok(ASTNode::BreakStatement(self.span_from(keyword.span)))
+        } else {
+            Ok(ASTNode::ContinueStatement(self.span_from(keyword.span)))
+        }
+    }
 
-        self.expect(TT::OpeningCurlyBrace);
+    fn parse_return_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let keyword = self.eat();
 
-        while self.peek().kind != TT::ClosingCurlyBrace {
-            body.push(self.parse());
+        if self.function_depth == 0 {
+            return Err(Diagnostic::new("'return' outside of a function".to_string(), keyword.span));
         }
-        self.eat();
 
-        self.expect(TT::SemiColon);
+        let value = if self.peek().kind == TT::SemiColon {
+            ASTNode::NullLiteral(keyword.span)
+        } else {
+            self.parse_expression()?
+        };
+        self.expect(TT::SemiColon)?;
 
-        ASTNode::Case(Box::new(case), Box::new(ASTNode::Program(body)))
+        Ok(ASTNode::ReturnStatement(Box::new(value), self.span_from(keyword.span)))
     }
 
-    fn parse_if_statement(&mut self) -> ASTNode {
+    fn parse_case_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
+
+        let value = self.parse_expression()?;
+
+        if !(self.peek().kind == TT::KeyWord && self.peek().value == "of") {
+            return Err(Diagnostic::new(format!("Expected an 'of', got '{:?}'", self.peek().kind), self.peek().span));
+        }
         self.eat();
 
-        let condition = self.parse_conditional_expression();
-        let mut body = Vec::new();
-        let mut else_body = Vec::new();
+        let mut cases = Vec::new();
 
-        self.expect(TT::OpeningCurlyBrace);
+        self.expect(TT::OpeningCurlyBrace)?;
 
         while self.peek().kind != TT::ClosingCurlyBrace {
-            body.push(self.parse());
+            cases.push(self.parse_case()?);
         }
-        self.eat();
+        self.expect(TT::ClosingCurlyBrace)?;
 
-        if self.peek().kind == TT::KeyWord && self.peek().value == "else" {
+        Ok(ASTNode::CaseStatement(Box::new(value), cases, self.span_from(start)))
+    }
+
+    fn parse_case(&mut self) -> Result<ASTNode, Diagnostic> {
+        let case = self.parse_expression()?;
+
+        self.expect(TT::Colon)?;
+        let body = self.parse_block()?;
+
+        // The ';' after each case is optional
+        if self.peek().kind == TT::SemiColon {
             self.eat();
-            self.expect(TT::OpeningCurlyBrace);
+        }
 
-            while self.peek().kind != TT::ClosingCurlyBrace {
-                else_body.push(self.parse());
-            }
+        let span = self.span_from(case.span());
+        Ok(ASTNode::Case(Box::new(case), Box::new(body), span))
+    }
+
+    fn parse_if_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
 
+        let condition = self.parse_expression()?;
+        let body = self.parse_block()?;
+
+        let else_body = if self.peek().kind == TT::KeyWord && self.peek().value == "else" {
             self.eat();
+
+            // `else if` becomes an else body holding only the next if statement
+            if self.peek().kind == TT::KeyWord && self.peek().value == "if" {
+                let else_start = self.peek().span;
+                let else_if = self.parse_if_statement()?;
+                ASTNode::Program(vec![else_if], self.span_from(else_start))
+            } else {
+                self.parse_block()?
+            }
+        } else {
+            ASTNode::Program(Vec::new(), self.peek().span)
         };
 
-        ASTNode::IfStatement(
+        Ok(ASTNode::IfStatement(
             Box::new(condition),
-            Box::new(ASTNode::Program(body)),
-            Box::new(ASTNode::Program(else_body)),
-        )
+            Box::new(body),
+            Box::new(else_body),
+            self.span_from(start),
+        ))
     }
 
-    fn parse_variable_declaration(&mut self) -> ASTNode {
-        self.eat();
+    fn parse_variable_declaration(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.eat().span;
 
-        if self.peek().kind == TT::Identifier || self.peek().kind == TT::OpeningParenthesis {
-            let variable_name = match self.peek().kind {
-                TT::Identifier => self.parse_primary_expression(),
-                _ => self.parse_call_expression(),
-            };
+        let variable_name = self.parse_pattern()?;
+
+        let variable_value = if self.peek().kind == TT::AssignmentOperator {
+            self.eat();
+            self.parse_expression()?
+        } else {
+            ASTNode::NullLiteral(self.peek().span)
+        };
+        self.expect(TT::SemiColon)?;
+
+        Ok(ASTNode::VariableDeclaration(Box::new(variable_name), Box::new(variable_value), self.span_from(start)))
+    }
+
+    // Parses the target of a `let`: a name, or a tuple `(a, b)`, array `[a, ...rest]` or object
+    // `{ a, b: c, ...rest }` pattern, which can be nested
+    fn parse_pattern(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.peek().span;
 
-            if self.peek().kind == TT::AssignmentOperator {
+        match self.peek().kind {
+            TT::Identifier => Ok(ASTNode::Identifier(self.eat().value, start)),
+            TT::OpeningParenthesis => {
                 self.eat();
-                let variable_value = self.parse_expression();
+                let mut patterns = vec![self.parse_pattern()?];
+                let mut tuple = false;
 
-                if self.peek().kind == TT::SemiColon {
+                while self.peek().kind == TT::Comma {
                     self.eat();
-                    ASTNode::VariableDeclaration(Box::new(variable_name), Box::new(variable_value))
+                    tuple = true;
+                    if self.peek().kind == TT::ClosingParenthesis {
+                        break;
+                    }
+                    patterns.push(self.parse_pattern()?);
+                }
+                self.expect(TT::ClosingParenthesis)?;
+
+                if tuple {
+                    Ok(ASTNode::TupleLiteral(patterns, self.span_from(start)))
                 } else {
-                    panic!("Expected ';', got '{:?}'", self.peek());
+                    Ok(patterns.remove(0))
                 }
-            } else if self.peek().kind == TT::SemiColon {
+            }
+            TT::OpeningSquareBracket => {
                 self.eat();
-                ASTNode::VariableDeclaration(
-                    Box::new(variable_name),
-                    Box::new(ASTNode::NullLiteral),
-                )
-            } else {
-                panic!("Expected ';' or an identifier, got '{}'", self.peek().value);
+                let mut patterns = Vec::new();
+                let mut rest = None;
+
+                while self.peek().kind != TT::ClosingSquareBracket {
+                    if self.peek().kind == TT::Ellipsis {
+                        self.eat();
+                        rest = Some(Box::new(self.parse_pattern()?));
+                        break;
+                    }
+                    patterns.push(self.parse_pattern()?);
+
+                    if self.peek().kind == TT::Comma {
+                        self.eat();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(TT::ClosingSquareBracket)?;
+                Ok(ASTNode::ArrayPattern(patterns, rest, self.span_from(start)))
+            }
+            TT::OpeningCurlyBrace => {
+                self.eat();
+                let mut properties = Vec::new();
+                let mut rest = None;
+
+                while self.peek().kind != TT::ClosingCurlyBrace {
+                    if self.peek().kind == TT::Ellipsis {
+                        self.eat();
+                        rest = Some(Box::new(self.parse_pattern()?));
+                        break;
+                    }
+                    let key = self.property_name()?;
+
+                    let pattern = if self.peek().kind == TT::Colon {
+                        self.eat();
+                        self.parse_pattern()?
+                    } else if key.kind == TT::Identifier {
+                        // Shorthand `{ b }` puts the property `b` in the variable `b`
+                        ASTNode::Identifier(key.value.clone(), key.span)
+                    } else {
+                        return Err(Diagnostic::new(format!("Expected a ':' after '{}'", key.value), self.peek().span));
+                    };
+                    properties.push((key.value, pattern));
+
+                    if self.peek().kind == TT::Comma {
+                        self.eat();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(TT::ClosingCurlyBrace)?;
+                Ok(ASTNode::ObjectPattern(properties, rest, self.span_from(start)))
             }
+            _ => Err(Diagnostic::new(
+                format!("Expected a variable name or a pattern, got '{}'", self.peek().value),
+                start,
+            )),
+        }
+    }
+
+    // Eats the key of an object literal or pattern, which is a name or a string
+    fn property_name(&mut self) -> Result<Token, Diagnostic> {
+        if [TT::Identifier, TT::KeyWord, TT::String].contains(&self.peek().kind) {
+            Ok(self.eat())
         } else {
-            panic!("Expected an identifier, got '{}'", self.peek().value);
+            Err(Diagnostic::new(format!("Expected a property name, got '{}'", self.peek().value), self.peek().span))
         }
     }
 
-    fn parse_expression(&mut self) -> ASTNode {
-        self.parse_assignment_expression()
+    fn parse_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        self.parse_operator_expression(0)
     }
 
-    fn parse_assignment_expression(&mut self) -> ASTNode {
+    // Assignments can only be used as statements, not inside other expressions
+    fn parse_assignment_expression(&mut self) -> Result<ASTNode, Diagnostic> {
         if self.peek().kind == TT::Identifier || self.peek().kind == TT::OpeningParenthesis {
-            let variable = self.parse_conditional_expression();
-            if self.peek().kind == TT::AssignmentOperator {
-                self.eat();
-                let variable_value = self.parse_expression();
-
-                if self.peek().kind == TT::SemiColon {
-                    self.eat();
-                    ASTNode::AssignmentExpression(Box::new(variable), Box::new(variable_value))
+            let variable = self.parse_expression()?;
+            if [TT::AssignmentOperator, TT::CompoundAssignmentOperator].contains(&self.peek().kind) {
+                let operator = self.eat();
+                let variable_value = self.parse_expression()?;
+                let span = self.span_from(variable.span());
+
+                if operator.kind == TT::AssignmentOperator {
+                    Ok(ASTNode::AssignmentExpression(Box::new(variable), Box::new(variable_value), span))
                 } else {
-                    panic!("Expected a ';', got a '{:?}'", self.eat());
+                    let operator = operator.value.trim_end_matches('=').to_string();
+                    Ok(ASTNode::CompoundAssignmentExpression(Box::new(variable), operator, Box::new(variable_value), span))
                 }
             } else {
-                variable
+                Ok(variable)
             }
         } else {
-            self.parse_conditional_expression()
+            self.parse_expression()
         }
     }
 
-    fn parse_conditional_expression(&mut self) -> ASTNode {
-        let mut left = self.parse_additive_expression();
+    // Parses operators by precedence climbing: an operator is only taken while it binds at least
+    // as tight as `min_power`, and its right side is parsed with a higher minimum so that tighter
+    // operators end up deeper in the tree. See `Operator` for the table.
+    fn parse_operator_expression(&mut self, min_power: u8) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_prefix_expression()?;
 
-        while [
-            TT::GreaterThan,
-            TT::LessThan,
-            TT::GreaterThanOrEqualTo,
-            TT::LessThanOrEqualTo,
-            TT::EqualityOperator,
-            TT::InEqualityOperator,
-        ]
-        .contains(&self.peek().kind)
-        {
-            let operator = self.eat().value;
-            let right = self.parse_additive_expression();
-            left = ASTNode::ConditionalExpression(Box::new(left), operator, Box::new(right));
-        }
-
-        left
-    }
-
-    fn parse_additive_expression(&mut self) -> ASTNode {
-        let mut left = self.parse_multiplicative_expression();
+        loop {
+            if [TT::OpeningParenthesis, TT::OpeningSquareBracket, TT::Dot].contains(&self.peek().kind) {
+                left = self.parse_postfix_expression(left)?;
+                continue;
+            }
 
-        while self.peek().kind == TT::BinaryOperator
-            && ["+", "-"].contains(&self.peek().value.as_str())
-        {
-            let operator = self.eat().value;
-            let right = self.parse_multiplicative_expression();
-            left = ASTNode::BinaryExpression(Box::new(left), operator, Box::new(right));
+            let Some(operator) = Operator::infix(self.peek()) else {
+                break;
+            };
+            let power = operator.power();
+            if power < min_power {
+                break;
+            }
+            let token = self.eat();
+
+            left = match operator {
+                Operator::Conversion => {
+                    let kind = self.expect(TT::Identifier)?;
+                    let right = ASTNode::Identifier(kind.value, kind.span);
+                    let span = self.span_from(left.span());
+                    ASTNode::ConversionExpression(Box::new(left), Box::new(right), span)
+                }
+                Operator::Range => {
+                    let right = self.parse_operator_expression(power + 1)?;
+                    if self.peek().kind == TT::Range {
+                        return Err(Diagnostic::new(
+                            format!("Ranges can't be chained, found '{}'", self.peek().value),
+                            self.peek().span,
+                        ));
+                    }
+                    let span = self.span_from(left.span());
+                    ASTNode::RangeExpression(Box::new(left), Box::new(right), token.value == "..=", span)
+                }
+                _ => {
+                    // Every other operator is left associative
+                    let right = self.parse_operator_expression(power + 1)?;
+                    let span = self.span_from(left.span());
+
+                    match operator {
+                        Operator::Or | Operator::Xor | Operator::And => {
+                            ASTNode::LogicalExpression(Box::new(left), token.value, Box::new(right), span)
+                        }
+                        Operator::Comparison => {
+                            ASTNode::ConditionalExpression(Box::new(left), token.value, Box::new(right), span)
+                        }
+                        _ => ASTNode::BinaryExpression(Box::new(left), token.value, Box::new(right), span),
+                    }
+                }
+            };
         }
-        left
+        Ok(left)
     }
 
-    fn parse_multiplicative_expression(&mut self) -> ASTNode {
-        let mut left = self.parse_call_expression();
+    fn parse_prefix_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.peek().span;
 
-        while self.peek().kind == TT::BinaryOperator
-            && ["*", "/", "%"].contains(&self.peek().value.as_str())
+        if [TT::BinaryOperator, TT::Not].contains(&self.peek().kind)
+            && ["+", "-", "!"].contains(&self.peek().value.as_str())
         {
-            let operator = self.eat().value;
-            let right = self.parse_call_expression();
-            left = ASTNode::BinaryExpression(Box::new(left), operator, Box::new(right));
+            let sign = self.eat().value;
+            let value = self.parse_operator_expression(Operator::PREFIX)?;
+
+            Ok(ASTNode::UnaryExpression(sign, Box::new(value), self.span_from(start)))
+        } else {
+            self.parse_primary_expression()
         }
-        left
     }
 
-    fn parse_call_expression(&mut self) -> ASTNode {
-        let mut calle = self.parse_member_expression();
-
+    // Calls, indexing and property access, which bind tighter than any other operator
+    fn parse_postfix_expression(&mut self, left: ASTNode) -> Result<ASTNode, Diagnostic> {
         if self.peek().kind == TT::OpeningParenthesis {
-            let arguments = self.parse_arguments();
-            calle = ASTNode::CallExpression(Box::new(calle), arguments)
+            let arguments = self.parse_arguments()?;
+            let span = self.span_from(left.span());
+            return Ok(ASTNode::CallExpression(Box::new(left), arguments, span));
         }
-        calle
+
+        let dot = self.eat().kind == TT::Dot;
+        let property = if dot {
+            if ![TT::Identifier, TT::KeyWord].contains(&self.peek().kind) {
+                return Err(Diagnostic::new(format!("Expected a property name, got '{}'", self.peek().value), self.peek().span));
+            }
+            let property = self.eat();
+            ASTNode::Identifier(property.value, property.span)
+        } else {
+            let index = self.parse_expression()?;
+            self.expect(TT::ClosingSquareBracket)?;
+            index
+        };
+        let span = self.span_from(left.span());
+        Ok(ASTNode::MemberExpression(Box::new(left), Box::new(property), dot, span))
     }
 
-    fn parse_arguments(&mut self) -> Vec<ASTNode> {
+    fn parse_arguments(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
         self.eat();
 
         let mut arguments = Vec::new();
 
         while self.peek().kind != TT::ClosingParenthesis {
-            let argument = self.parse_expression();
+            let argument = self.parse_expression()?;
             arguments.push(argument);
             if self.peek().kind == TT::Comma {
                 self.eat();
             } else if self.peek().kind != TT::ClosingParenthesis {
-                panic!("Expected a ',', or a ')', but found a {:?}.", self.peek());
+                return Err(Diagnostic::new(
+                    format!("Expected a ',', or a ')', but found a {:?}", self.peek().kind),
+                    self.peek().span,
+                ));
             }
         }
         self.eat();
-        arguments
-    }
-
-    fn parse_member_expression(&mut self) -> ASTNode {
-        let mut left = self.parse_conversion_expression();
-
-        while self.peek().kind == TT::Dot || self.peek().kind == TT::OpeningSquareBracket {
-            let operator = self.eat();
-            let right;
-
-            if operator.kind == TT::Dot {
-                right = self.parse_conversion_expression();
-            } else {
-                right = self.parse_expression();
-                self.expect(TT::ClosingSquareBracket);
-            }
-            left =
-                ASTNode::MemberExpression(Box::new(left), Box::new(right), operator.kind == TT::Dot)
-        }
-        left
-    }
-
-    fn parse_conversion_expression(&mut self) -> ASTNode {
-        let left = self.parse_primary_expression();
-
-        if self.peek().kind == TT::KeyWord
-            && (self.peek().value == "as" || self.peek().value == "to")
-        {
-            self.eat();
-
-            let right = self.parse_primary_expression();
-            ASTNode::ConversionExpression(Box::new(left), Box::new(right))
-        } else {
-            left
-        }
+        Ok(arguments)
     }
 
-    fn parse_primary_expression(&mut self) -> ASTNode {
-        match self.peek().kind {
-            TT::Identifier => ASTNode::Identifier(self.eat().value),
-            TT::Integer => ASTNode::IntegerLiteral(self.eat().value.parse::<i64>().unwrap()),
-            TT::Real => ASTNode::RealLiteral(self.eat().value.parse::<f64>().unwrap()),
-            TT::String => ASTNode::StringLiteral(self.eat().value),
-            TT::BinaryOperator | TT::Not => match self.peek().value.as_str() {
-                "+" | "-" | "!" => {
-                    let sign = self.eat().value;
-                    let value = self.parse_primary_expression();
-
-                    ASTNode::UnaryExpression(sign, Box::new(value))
+    fn parse_primary_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.peek().span;
+
+        let node = match self.peek().kind {
+            TT::Identifier => ASTNode::Identifier(self.eat().value, start),
+            TT::KeyWord if self.peek().value == "func" => self.parse_function_expression()?,
+            // In expression position `if` and `case` have the value of the branch that was taken
+            TT::KeyWord if self.peek().value == "if" => self.parse_if_statement()?,
+            TT::KeyWord if self.peek().value == "case" => self.parse_case_statement()?,
+            TT::Integer => ASTNode::IntegerLiteral(self.eat().value.parse::<i64>().unwrap(), start),
+            TT::Real => ASTNode::RealLiteral(self.eat().value.parse::<f64>().unwrap(), start),
+            TT::String => ASTNode::StringLiteral(self.eat().value, start),
+            TT::StringStart => {
+                self.eat();
+                let mut parts = Vec::new();
+
+                while self.peek().kind != TT::StringEnd {
+                    match self.peek().kind {
+                        TT::String => {
+                            let part = self.eat();
+                            parts.push(ASTNode::StringLiteral(part.value, part.span));
+                        }
+                        _ => {
+                            self.expect(TT::InterpolationStart)?;
+                            parts.push(self.parse_expression()?);
+                            self.expect(TT::InterpolationEnd)?;
+                        }
+                    }
                 }
-                _ => panic!("Invalid token '{:?}' found", self.eat()),
-            },
+                self.eat();
+                ASTNode::InterpolatedString(parts, self.span_from(start))
+            }
             TT::OpeningSquareBracket => {
                 self.eat();
                 let mut values = Vec::new();
 
                 while self.peek().kind != TT::ClosingSquareBracket {
-                    let node = self.parse_expression();
+                    let node = self.parse_expression()?;
                     values.push(node);
 
                     if self.peek().kind == TT::Comma {
@@ -414,20 +877,209 @@ impl Parser {
                         break;
                     };
                 }
-                self.expect(TT::ClosingSquareBracket);
-                ASTNode::ArrayLiteral(values)
+                self.expect(TT::ClosingSquareBracket)?;
+                ASTNode::ArrayLiteral(values, self.span_from(start))
+            }
+            TT::OpeningCurlyBrace => {
+                self.eat();
+                let mut properties = Vec::new();
+
+                while self.peek().kind != TT::ClosingCurlyBrace {
+                    let key = self.property_name()?;
+
+                    let value = if self.peek().kind == TT::Colon {
+                        self.eat();
+                        self.parse_expression()?
+                    } else if key.kind == TT::Identifier {
+                        // Shorthand `{ b }` takes the value of the variable `b`
+                        ASTNode::Identifier(key.value.clone(), key.span)
+                    } else {
+                        return Err(Diagnostic::new(format!("Expected a ':' after '{}'", key.value), self.peek().span));
+                    };
+                    properties.push((key.value, value));
+
+                    if self.peek().kind == TT::Comma {
+                        self.eat();
+                    } else {
+                        break;
+                    };
+                }
+                self.expect(TT::ClosingCurlyBrace)?;
+                ASTNode::ObjectLiteral(properties, self.span_from(start))
             }
             TT::OpeningParenthesis => {
                 self.eat();
                 if self.peek().kind == TT::ClosingParenthesis {
                     self.eat();
-                    return ASTNode::NullLiteral;
+                    return Ok(ASTNode::NullLiteral(self.span_from(start)));
                 };
-                let node = self.parse_expression();
-                self.expect(TT::ClosingParenthesis);
+                let node = self.parse_expression()?;
+
+                // A comma makes it a tuple, `(a,)` has a single element
+                if self.peek().kind == TT::Comma {
+                    let mut values = vec![node];
+
+                    while self.peek().kind == TT::Comma {
+                        self.eat();
+                        if self.peek().kind == TT::ClosingParenthesis {
+                            break;
+                        }
+                        values.push(self.parse_expression()?);
+                    }
+                    self.expect(TT::ClosingParenthesis)?;
+                    return Ok(ASTNode::TupleLiteral(values, self.span_from(start)));
+                }
+                self.expect(TT::ClosingParenthesis)?;
                 node
             }
-            _ => panic!("Invalid token '{:?}' found", self.eat()),
+            _ => {
+                let token = self.peek();
+                return Err(Diagnostic::new(format!("Invalid token '{}' found", token.value), token.span));
+            }
+        };
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    // Parses a single expression and writes its tree with every operator in prefix position,
+    // leaving out spans so only the shape is compared
+    fn parse(source: &str) -> Result<String, Vec<Diagnostic>> {
+        let (tokens, diagnostics) = Lexer::new(source.to_string()).tokenize();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        match Parser::new(tokens).generate_ast() {
+            (ASTNode::Program(body, _), errors) if errors.is_empty() => {
+                assert_eq!(body.len(), 1, "expected a single expression in {:?}", source);
+                Ok(shape(&body[0]))
+            }
+            (_, errors) => Err(errors),
         }
     }
+
+    fn shape(node: &ASTNode) -> String {
+        match node {
+            ASTNode::IntegerLiteral(value, _) => value.to_string(),
+            ASTNode::Identifier(name, _) => name.clone(),
+            ASTNode::UnaryExpression(operator, operand, _) => format!("({} {})", operator, shape(operand)),
+            ASTNode::BinaryExpression(left, operator, right, _)
+            | ASTNode::ConditionalExpression(left, operator, right, _)
+            | ASTNode::LogicalExpression(left, operator, right, _) => {
+                format!("({} {} {})", operator, shape(left), shape(right))
+            }
+            ASTNode::ConversionExpression(value, kind, _) => format!("(as {} {})", shape(value), shape(kind)),
+            ASTNode::RangeExpression(start, end, inclusive, _) => {
+                format!("({} {} {})", if *inclusive { "..=" } else { ".." }, shape(start), shape(end))
+            }
+            ASTNode::AssignmentExpression(target, value, _) => format!("(= {} {})", shape(target), shape(value)),
+            ASTNode::CompoundAssignmentExpression(target, operator, value, _) => {
+                format!("({}= {} {})", operator, shape(target), shape(value))
+            }
+            ASTNode::MemberExpression(object, property, true, _) => format!("(. {} {})", shape(object), shape(property)),
+            ASTNode::MemberExpression(object, property, false, _) => format!("([] {} {})", shape(object), shape(property)),
+            ASTNode::CallExpression(callee, arguments, _) => {
+                let mut call = format!("(call {}", shape(callee));
+                for argument in arguments {
+                    call.push(' ');
+                    call.push_str(&shape(argument));
+                }
+                call + ")"
+            }
+            node => panic!("unexpected node {:?}", node),
+        }
+    }
+
+    // One test per row of the operator table, from the loosest to the tightest binding
+
+    #[test]
+    fn assignment_binds_loosest_and_cannot_be_chained() {
+        assert_eq!(parse("a = 1 | 2").unwrap(), "(= a (| 1 2))");
+        assert_eq!(parse("a += 1 | 2").unwrap(), "(+= a (| 1 2))");
+        assert!(parse("a = b = c;").is_err());
+        assert!(parse("let x = a = 1;").is_err());
+    }
+
+    #[test]
+    fn or_binds_looser_than_xor_and_and() {
+        assert_eq!(parse("a | b & c").unwrap(), "(| a (& b c))");
+        assert_eq!(parse("a | b ^ c & d").unwrap(), "(| a (^ b (& c d)))");
+        assert_eq!(parse("a | b | c").unwrap(), "(| (| a b) c)");
+    }
+
+    #[test]
+    fn xor_binds_looser_than_and() {
+        assert_eq!(parse("a ^ b & c").unwrap(), "(^ a (& b c))");
+        assert_eq!(parse("a ^ b ^ c").unwrap(), "(^ (^ a b) c)");
+    }
+
+    #[test]
+    fn and_binds_looser_than_comparison() {
+        assert_eq!(parse("x > 0 & x < 10").unwrap(), "(& (> x 0) (< x 10))");
+        assert_eq!(parse("a & b & c").unwrap(), "(& (& a b) c)");
+    }
+
+    #[test]
+    fn comparison_binds_looser_than_range() {
+        assert_eq!(parse("x in 0..n + 1").unwrap(), "(in x (.. 0 (+ n 1)))");
+        assert_eq!(parse("a == b == c").unwrap(), "(== (== a b) c)");
+    }
+
+    #[test]
+    fn range_binds_looser_than_additive_and_cannot_be_chained() {
+        assert_eq!(parse("0..=n - 1").unwrap(), "(..= 0 (- n 1))");
+        assert!(parse("0..1..2").is_err());
+    }
+
+    #[test]
+    fn additive_binds_looser_than_multiplicative() {
+        assert_eq!(parse("1 + 2 * 3").unwrap(), "(+ 1 (* 2 3))");
+        assert_eq!(parse("1 - 2 - 3").unwrap(), "(- (- 1 2) 3)");
+    }
+
+    #[test]
+    fn multiplicative_binds_looser_than_conversion() {
+        assert_eq!(parse("2 * 3 as real").unwrap(), "(* 2 (as 3 real))");
+        assert_eq!(parse("2 + 3 as string").unwrap(), "(+ 2 (as 3 string))");
+        assert_eq!(parse("8 / 4 / 2").unwrap(), "(/ (/ 8 4) 2)");
+    }
+
+    #[test]
+    fn conversion_binds_looser_than_prefix() {
+        assert_eq!(parse("-x as real").unwrap(), "(as (- x) real)");
+        assert_eq!(parse("x as integer as string").unwrap(), "(as (as x integer) string)");
+    }
+
+    #[test]
+    fn prefix_binds_looser_than_postfix() {
+        assert_eq!(parse("-a.b").unwrap(), "(- (. a b))");
+        assert_eq!(parse("!-x").unwrap(), "(! (- x))");
+        assert_eq!(parse("arr[0] as real").unwrap(), "(as ([] arr 0) real)");
+    }
+
+    #[test]
+    fn postfix_operators_chain_left_to_right() {
+        assert_eq!(parse("f()()").unwrap(), "(call (call f))");
+        assert_eq!(parse("obj.m().n()").unwrap(), "(call (. (call (. obj m)) n))");
+        assert_eq!(parse("grid[1][2]").unwrap(), "([] ([] grid 1) 2)");
+    }
+
+    #[test]
+    fn missing_semicolon_does_not_hide_the_next_statement() {
+        let errors = parse("let b = 2\nlet c = ;\nlet d = ;").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|error| error.span.line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn tokens_the_lexer_could_not_read_are_skipped_without_a_second_error() {
+        let (tokens, diagnostics) = Lexer::new("let a = @;\nlet b = ;".to_string()).tokenize();
+        let (_, errors) = Parser::new(tokens).generate_ast();
+
+        assert_eq!(diagnostics.iter().map(|error| error.span.line).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(errors.iter().map(|error| error.span.line).collect::<Vec<_>>(), vec![2]);
+    }
 }
@@ -1,10 +1,10 @@
 use crate::{methods::*, parser::ASTNode};
-use std::{
-    collections::HashMap,
-    fmt::{self, Write},
-};
+use std::{collections::HashMap, fmt};
+
+pub type MethodCall = fn(RuntimeValue, Vec<RuntimeValue>) -> Result<RuntimeValue, String>;
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(unpredictable_function_pointer_comparisons)]
 pub enum RuntimeValue {
     // Primitive datatypes
     Null,
@@ -15,27 +15,29 @@ pub enum RuntimeValue {
     Tuple(Vec<RuntimeValue>),
 
     // datatypes with methods
-    String(
-        String,
-        HashMap<String, fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue>,
-    ),
-    Array(
-        Vec<RuntimeValue>,
-        HashMap<String, fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue>,
-    ),
-    Object(
-        HashMap<String, RuntimeValue>,
-        HashMap<String, fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue>,
-    ),
+    String(String, HashMap<String, MethodCall>),
+    Array(Vec<RuntimeValue>, HashMap<String, MethodCall>),
+    Object(HashMap<String, RuntimeValue>, HashMap<String, MethodCall>),
+    // start, end, step and whether the end is included, the values are only produced when needed
+    Range(i64, i64, i64, bool, HashMap<String, MethodCall>),
 
     // datatypes the programmer can't access
     BuiltInFunction(fn(Vec<RuntimeValue>) -> RuntimeValue, Vec<RuntimeValue>),
-    Method(
-        fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
-        Box<RuntimeValue>,
+    Method(MethodCall, Box<RuntimeValue>, Vec<RuntimeValue>),
+    // parameters, parameter types, return type, body, captured variables and doc comment
+    Function(
+        Vec<ASTNode>,
         Vec<RuntimeValue>,
+        Box<RuntimeValue>,
+        ASTNode,
+        HashMap<String, RuntimeValue>,
+        Option<String>,
     ),
-    Function(Vec<ASTNode>, Vec<RuntimeValue>, Box<RuntimeValue>, ASTNode),
+
+    // control flow signals, passed up from a statement until the enclosing loop handles them
+    Break,
+    Continue,
+    Return(Box<RuntimeValue>),
 }
 
 impl fmt::Display for RuntimeValue {
@@ -51,10 +53,27 @@ impl fmt::Display for RuntimeValue {
                 res.fmt(f)
             }
             RuntimeValue::Null => write!(f, "null"),
-            RuntimeValue::Object(o, _) => write!(f, "{:?}", o),
+            RuntimeValue::Object(o, _) => {
+                let mut keys: Vec<&String> = o.keys().collect();
+                keys.sort();
+                let elements: Vec<String> = keys.iter().map(|k| format!("{}: {}", k, o[*k])).collect();
+                let res = format!("{{{}}}", elements.join(", "));
+                res.fmt(f)
+            }
+            RuntimeValue::Range(start, end, step, inclusive, _) => {
+                let operator = if *inclusive { "..=" } else { ".." };
+                if *step == 1 {
+                    write!(f, "{}{}{}", start, operator, end)
+                } else {
+                    write!(f, "({}{}{}).step({})", start, operator, end, step)
+                }
+            }
             RuntimeValue::BuiltInFunction(c, _) => write!(f, "{:?}", c),
             RuntimeValue::Tuple(t) => write!(f, "{:?}", t),
-            RuntimeValue::Function(p, _, _, b) => write!(f, "({:?}) {{{:?}}}", p, b),
+            RuntimeValue::Function(p, _, _, b, _, _) => write!(f, "({:?}) {{{:?}}}", p, b),
+            RuntimeValue::Break => write!(f, "break"),
+            RuntimeValue::Continue => write!(f, "continue"),
+            RuntimeValue::Return(value) => write!(f, "{}", value),
             RuntimeValue::Method(call, object, arguments) => {
                 let elements: Vec<String> = arguments.iter().map(|v| format!("{}", v)).collect();
                 let res = format!("{}.{:?}({})", object, call, elements.join(", "));
@@ -65,16 +84,21 @@ impl fmt::Display for RuntimeValue {
 }
 
 impl RuntimeValue {
+    // Whether the value is a control flow signal that should stop the current block
+    pub fn is_signal(&self) -> bool {
+        matches!(self, RuntimeValue::Break | RuntimeValue::Continue | RuntimeValue::Return(_))
+    }
+
     // Function to make and add methods to array
     pub fn array(values: Vec<RuntimeValue>) -> Self {
         let mut methods = HashMap::new();
         methods.insert(
             "length".to_string(),
-            array_length as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            array_length as MethodCall,
         );
         methods.insert(
             "is_empty".to_string(),
-            array_is_empty as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            array_is_empty as MethodCall,
         );
 
         RuntimeValue::Array(values, methods)
@@ -85,15 +109,15 @@ impl RuntimeValue {
         let mut methods = HashMap::new();
         methods.insert(
             "length".to_string(),
-            string_length as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            string_length as MethodCall,
         );
         methods.insert(
             "is_empty".to_string(),
-            string_is_empty as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            string_is_empty as MethodCall,
         );
         methods.insert(
             "split".to_string(),
-            string_split as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            string_split as MethodCall,
         );
 
         RuntimeValue::String(values, methods)
@@ -104,13 +128,60 @@ impl RuntimeValue {
         let mut methods = HashMap::new();
         methods.insert(
             "length".to_string(),
-            object_length as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            object_length as MethodCall,
         );
         methods.insert(
             "is_empty".to_string(),
-            object_is_empty as fn(RuntimeValue, Vec<RuntimeValue>) -> RuntimeValue,
+            object_is_empty as MethodCall,
         );
 
         RuntimeValue::Object(values, methods)
     }
+
+    // Function to make and add methods to range
+    pub fn range(start: i64, end: i64, step: i64, inclusive: bool) -> Self {
+        let mut methods = HashMap::new();
+        methods.insert("step".to_string(), range_step as MethodCall);
+        methods.insert("length".to_string(), range_length as MethodCall);
+        methods.insert("is_empty".to_string(), range_is_empty as MethodCall);
+
+        RuntimeValue::Range(start, end, step, inclusive, methods)
+    }
+}
+
+// Lazily produces the values of a range, counting down when the step is negative
+pub fn range_values(start: i64, end: i64, step: i64, inclusive: bool) -> impl Iterator<Item = i64> {
+    std::iter::successors(Some(start), move |i| i.checked_add(step)).take_while(move |&i| {
+        match (step > 0, inclusive) {
+            (true, true) => i <= end,
+            (true, false) => i < end,
+            (false, true) => i >= end,
+            (false, false) => i > end,
+        }
+    })
+}
+
+// Number of values in a range, computed without producing them
+pub fn range_count(start: i64, end: i64, step: i64, inclusive: bool) -> i64 {
+    let distance = if step > 0 {
+        end as i128 - start as i128
+    } else {
+        start as i128 - end as i128
+    } + inclusive as i128;
+    let step = (step as i128).abs();
+
+    if distance <= 0 {
+        0
+    } else {
+        ((distance + step - 1) / step) as i64
+    }
+}
+
+// Whether `value` is one of the values of a range
+pub fn range_contains(start: i64, end: i64, step: i64, inclusive: bool, value: i64) -> bool {
+    let offset = value as i128 - start as i128;
+
+    offset % step as i128 == 0
+        && offset / step as i128 >= 0
+        && offset / (step as i128) < range_count(start, end, step, inclusive) as i128
 }